@@ -0,0 +1,41 @@
+use mavlink_rust_edu::{Fence, RallyPoint, Vehicle};
+
+const SERVER_ADDRESS: &str = "tcpout:127.0.0.1:14550";
+
+fn main() {
+    println!("GSC > Started...");
+    let vehicle = Vehicle::connect(SERVER_ADDRESS).unwrap();
+    println!("GSC > Connected to {}", SERVER_ADDRESS);
+
+    let fence = Fence::new()
+        .inclusion_polygon([
+            (-35.3590, 149.1610),
+            (-35.3590, 149.1700),
+            (-35.3680, 149.1700),
+            (-35.3680, 149.1610),
+        ])
+        .exclusion_circle(-35.3632, 149.1652, 30.0)
+        .return_point(-35.3620, 149.1640, 50.0);
+    println!("GSC > Uploading the fence");
+    vehicle.upload_fence(&fence).unwrap();
+    let fence = vehicle.download_fence().unwrap();
+    for zone in &fence.zones {
+        println!("Vehicle > fence zone: {zone:?}");
+    }
+    if let Some((position, altitude)) = fence.return_point {
+        println!("Vehicle > fence return point: {position} at {altitude} m");
+    }
+
+    let rally_points = [
+        RallyPoint::new(-35.3600, 149.1620, 40.0),
+        RallyPoint::new(-35.3670, 149.1690, 40.0),
+    ];
+    println!("GSC > Uploading {} rally points", rally_points.len());
+    vehicle.upload_rally_points(&rally_points).unwrap();
+    for point in vehicle.download_rally_points().unwrap() {
+        println!(
+            "Vehicle > rally point: {} at {} m",
+            point.position, point.altitude
+        );
+    }
+}
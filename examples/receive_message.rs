@@ -1,6 +1,7 @@
 use std::{thread, time::Duration};
 
 use mavlink::error::MessageReadError;
+use mavlink_rust_edu::GlobalPosition;
 
 const SERVER_ADDRESS: &str = "tcpout:127.0.0.1:14550";
 
@@ -16,12 +17,13 @@ fn main() {
                     println!("HEARTBEAT, system status: {:?}", data.system_status);
                 }
                 mavlink::ardupilotmega::MavMessage::GLOBAL_POSITION_INT(data) => {
+                    let position = GlobalPosition::from(&data);
                     println!(
                         "GLOBAL_POSITION_INT, lat: {:?}, lon: {:?}, alt: {:?}, relative_alt: {:?}",
-                        (data.lat as f64) / 10_000_000.0,
-                        (data.lon as f64) / 10_000_000.0,
-                        (data.alt as f64) / 1_000.0,
-                        (data.relative_alt as f64) / 1_000.0
+                        position.position.latitude,
+                        position.position.longitude,
+                        position.altitude,
+                        position.relative_altitude
                     );
                 }
                 mavlink::ardupilotmega::MavMessage::ATTITUDE(data) => {
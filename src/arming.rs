@@ -0,0 +1,225 @@
+use std::time::{Duration, Instant};
+
+use mavlink::ardupilotmega::{MavCmd, MavMessage, MavModeFlag, STATUSTEXT_DATA};
+
+use crate::{
+    error::{Error, Result},
+    station::Subscription,
+    vehicle::Vehicle,
+};
+
+/// How long to wait for a HEARTBEAT showing the new armed state after the ACK.
+const ARM_CONFIRM_TIMEOUT: Duration = Duration::from_secs(3);
+/// STATUSTEXT explaining a rejection may trail the ACK slightly.
+const STATUSTEXT_LINGER: Duration = Duration::from_millis(200);
+/// Param2 of MAV_CMD_COMPONENT_ARM_DISARM that skips the pre-arm checks, or
+/// disarms in flight.
+const FORCE_MAGIC: f32 = 21196.0;
+
+impl Vehicle {
+    /// Arms and waits until a HEARTBEAT reports MAV_MODE_FLAG_SAFETY_ARMED.
+    ///
+    /// If the vehicle refuses, the "PreArm:" and "Arm:" STATUSTEXT lines it
+    /// sent are returned in [`Error::ArmingRejected`].
+    pub fn arm(&self) -> Result<()> {
+        self.set_armed(true, false)
+    }
+
+    /// Arms even if pre-arm checks fail.
+    pub fn force_arm(&self) -> Result<()> {
+        self.set_armed(true, true)
+    }
+
+    /// Disarms and waits until a HEARTBEAT no longer reports the armed flag.
+    pub fn disarm(&self) -> Result<()> {
+        self.set_armed(false, false)
+    }
+
+    /// Disarms even while flying, which drops the vehicle.
+    pub fn force_disarm(&self) -> Result<()> {
+        self.set_armed(false, true)
+    }
+
+    fn set_armed(&self, arm: bool, force: bool) -> Result<()> {
+        let messages = self.subscribe();
+        let params = [
+            if arm { 1.0 } else { 0.0 },
+            if force { FORCE_MAGIC } else { 0.0 },
+            0.0,
+            0.0,
+            0.0,
+            0.0,
+            0.0,
+        ];
+        match self.send_command(MavCmd::MAV_CMD_COMPONENT_ARM_DISARM, params) {
+            Ok(()) => {}
+            Err(Error::CommandFailed { result, .. }) => {
+                return Err(Error::ArmingRejected {
+                    arm,
+                    result,
+                    reasons: self.collect_arming_reasons(&messages)?,
+                });
+            }
+            Err(e) => return Err(e),
+        }
+        let component_id = self.component_id();
+        self.subscribe().wait_for(
+            ARM_CONFIRM_TIMEOUT,
+            "HEARTBEAT with the new arming state",
+            |header, msg| match msg {
+                MavMessage::HEARTBEAT(data)
+                    if header.component_id == component_id
+                        && data
+                            .base_mode
+                            .contains(MavModeFlag::MAV_MODE_FLAG_SAFETY_ARMED)
+                            == arm =>
+                {
+                    Some(())
+                }
+                _ => None,
+            },
+        )
+    }
+
+    /// Drains `messages` for a moment longer, keeping the arming check
+    /// failures and joining STATUSTEXT chunks that share an id.
+    fn collect_arming_reasons(&self, messages: &Subscription) -> Result<Vec<String>> {
+        let mut lines: Vec<(u16, String)> = Vec::new();
+        let deadline = Instant::now() + STATUSTEXT_LINGER;
+        loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            let Some((header, msg)) = messages.recv_timeout(remaining)? else {
+                break;
+            };
+            let MavMessage::STATUSTEXT(data) = msg else {
+                continue;
+            };
+            if header.component_id != self.component_id() {
+                continue;
+            }
+            let text = statustext(&data);
+            match lines.last_mut() {
+                Some((id, line)) if data.id != 0 && *id == data.id => line.push_str(&text),
+                _ => lines.push((data.id, text)),
+            }
+        }
+        Ok(lines
+            .into_iter()
+            .map(|(_, line)| line)
+            .filter(|line| line.starts_with("PreArm:") || line.starts_with("Arm:"))
+            .collect())
+    }
+}
+
+fn statustext(data: &STATUSTEXT_DATA) -> String {
+    let len = data
+        .text
+        .iter()
+        .position(|&b| b == 0)
+        .unwrap_or(data.text.len());
+    String::from_utf8_lossy(&data.text[..len]).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    };
+
+    use mavlink::ardupilotmega::{HEARTBEAT_DATA, MavResult};
+
+    use super::*;
+    use crate::mock::{self, MockPeer, command_ack};
+
+    const ARM_DISARM: MavCmd = MavCmd::MAV_CMD_COMPONENT_ARM_DISARM;
+
+    fn statustext(text: &str, id: u16, chunk_seq: u8) -> MavMessage {
+        let mut data = STATUSTEXT_DATA {
+            id,
+            chunk_seq,
+            ..Default::default()
+        };
+        data.text[..text.len()].copy_from_slice(text.as_bytes());
+        MavMessage::STATUSTEXT(data)
+    }
+
+    /// A vehicle whose HEARTBEATs show it armed once it accepts an arm
+    /// command, and disarmed again only if `disarms`.
+    fn vehicle(disarms: bool) -> (Vehicle, MockPeer) {
+        let armed = Arc::new(AtomicBool::new(false));
+        let reported = armed.clone();
+        let (vehicle, peer) = mock::vehicle(move |msg| match msg {
+            MavMessage::COMMAND_LONG(data) if data.command == ARM_DISARM => {
+                if data.param1 == 1.0 || disarms {
+                    armed.store(data.param1 == 1.0, Ordering::Relaxed);
+                }
+                vec![command_ack(ARM_DISARM, MavResult::MAV_RESULT_ACCEPTED)]
+            }
+            _ => Vec::new(),
+        });
+        peer.send_heartbeats(move || HEARTBEAT_DATA {
+            base_mode: if reported.load(Ordering::Relaxed) {
+                MavModeFlag::MAV_MODE_FLAG_SAFETY_ARMED
+            } else {
+                MavModeFlag::empty()
+            },
+            ..Default::default()
+        });
+        (vehicle, peer)
+    }
+
+    fn force_param(peer: &MockPeer) -> f32 {
+        match &peer.sent()[0] {
+            MavMessage::COMMAND_LONG(data) => data.param2,
+            other => panic!("sent {other:?}"),
+        }
+    }
+
+    #[test]
+    fn arms_and_disarms_once_the_heartbeat_agrees() {
+        let (vehicle, peer) = vehicle(true);
+        vehicle.arm().unwrap();
+        assert_eq!(force_param(&peer), 0.0);
+        vehicle.disarm().unwrap();
+    }
+
+    #[test]
+    fn force_arm_sets_the_magic_number() {
+        let (vehicle, peer) = vehicle(true);
+        vehicle.force_arm().unwrap();
+        assert_eq!(force_param(&peer), FORCE_MAGIC);
+    }
+
+    #[test]
+    fn times_out_while_the_vehicle_stays_armed() {
+        let (vehicle, _peer) = vehicle(false);
+        vehicle.arm().unwrap();
+        assert!(matches!(vehicle.disarm(), Err(Error::Timeout(_))));
+    }
+
+    #[test]
+    fn rejection_carries_the_joined_prearm_failures() {
+        let (vehicle, _peer) = mock::vehicle(|msg| match msg {
+            MavMessage::COMMAND_LONG(data) if data.command == ARM_DISARM => vec![
+                statustext("PreArm: Gyros ", 7, 0),
+                command_ack(ARM_DISARM, MavResult::MAV_RESULT_FAILED),
+                statustext("inconsistent", 7, 1),
+                statustext("Arm: Battery below minimum", 0, 0),
+                statustext("EKF3 IMU0 initialised", 0, 0),
+            ],
+            _ => Vec::new(),
+        });
+        match vehicle.arm() {
+            Err(Error::ArmingRejected {
+                arm: true,
+                result: MavResult::MAV_RESULT_FAILED,
+                reasons,
+            }) => assert_eq!(
+                reasons,
+                ["PreArm: Gyros inconsistent", "Arm: Battery below minimum"]
+            ),
+            other => panic!("arm returned {other:?}"),
+        }
+    }
+}
@@ -0,0 +1,474 @@
+use std::time::Duration;
+
+use mavlink::ardupilotmega::{
+    COMMAND_ACK_DATA, COMMAND_CANCEL_DATA, COMMAND_INT_DATA, COMMAND_LONG_DATA, MavCmd, MavFrame,
+    MavMessage, MavResult,
+};
+
+use crate::{
+    error::{Error, Result},
+    geo::LatLon,
+    station::Subscription,
+    vehicle::Vehicle,
+};
+
+const ACK_TIMEOUT: Duration = Duration::from_millis(1500);
+const COMMAND_RETRIES: u8 = 3;
+/// How long a MAV_RESULT_IN_PROGRESS command may stay silent between updates.
+const PROGRESS_TIMEOUT: Duration = Duration::from_secs(10);
+
+impl Vehicle {
+    /// Sends `command` as COMMAND_LONG and blocks until the vehicle acknowledges it.
+    ///
+    /// Unanswered commands are resent with an incremented `confirmation`, as the
+    /// MAVLink command protocol requires. Any result other than
+    /// MAV_RESULT_ACCEPTED is returned as [`Error::CommandFailed`].
+    pub fn send_command(&self, command: MavCmd, params: [f32; 7]) -> Result<()> {
+        self.send_command_with_progress(command, params, |_| {})
+    }
+
+    /// Like [`Vehicle::send_command`], but keeps waiting through
+    /// MAV_RESULT_IN_PROGRESS acks, passing each `progress` (0-100, or 255 if
+    /// the vehicle cannot estimate it) to `on_progress`.
+    pub fn send_command_with_progress(
+        &self,
+        command: MavCmd,
+        params: [f32; 7],
+        on_progress: impl FnMut(u8),
+    ) -> Result<()> {
+        self.run_command(command, on_progress, |confirmation| {
+            MavMessage::COMMAND_LONG(COMMAND_LONG_DATA {
+                target_system: self.system_id(),
+                target_component: self.component_id(),
+                command,
+                param1: params[0],
+                param2: params[1],
+                param3: params[2],
+                param4: params[3],
+                param5: params[4],
+                param6: params[5],
+                param7: params[6],
+                confirmation,
+            })
+        })
+    }
+
+    /// Sends a positional command as COMMAND_INT so latitude and longitude
+    /// keep their full degE7 precision. Autopilots that answer
+    /// MAV_RESULT_UNSUPPORTED get the same command again as COMMAND_LONG.
+    pub fn send_position_command(&self, position_command: &PositionCommand) -> Result<()> {
+        let command = position_command.command;
+        let command_int = position_command.to_command_int(self)?;
+        let result = self.run_command(
+            command,
+            |_| {},
+            |_| MavMessage::COMMAND_INT(command_int.clone()),
+        );
+        match result {
+            Err(Error::CommandFailed {
+                result: MavResult::MAV_RESULT_UNSUPPORTED,
+                ..
+            }) => self.send_command(command, position_command.to_command_long_params()),
+            result => result,
+        }
+    }
+
+    /// Asks the vehicle to abort a long-running `command`. The caller waiting
+    /// on that command then gets MAV_RESULT_CANCELLED.
+    pub fn cancel_command(&self, command: MavCmd) -> Result<()> {
+        self.send(&MavMessage::COMMAND_CANCEL(COMMAND_CANCEL_DATA {
+            target_system: self.system_id(),
+            target_component: self.component_id(),
+            command,
+        }))
+    }
+
+    /// Drives one command transaction; `message` builds the attempt numbered
+    /// by its argument, which COMMAND_LONG uses as `confirmation`.
+    fn run_command(
+        &self,
+        command: MavCmd,
+        mut on_progress: impl FnMut(u8),
+        message: impl Fn(u8) -> MavMessage,
+    ) -> Result<()> {
+        let messages = self.subscribe();
+        let mut ack = None;
+        for confirmation in 0..=COMMAND_RETRIES {
+            self.send(&message(confirmation))?;
+            match self.wait_for_ack(&messages, command, ACK_TIMEOUT) {
+                Ok(data) => {
+                    ack = Some(data);
+                    break;
+                }
+                Err(Error::Timeout(_)) => continue,
+                Err(e) => return Err(e),
+            }
+        }
+        let mut ack = ack.ok_or(Error::Timeout("COMMAND_ACK"))?;
+        while ack.result == MavResult::MAV_RESULT_IN_PROGRESS {
+            on_progress(ack.progress);
+            ack = self.wait_for_ack(&messages, command, PROGRESS_TIMEOUT)?;
+        }
+        match ack.result {
+            MavResult::MAV_RESULT_ACCEPTED => Ok(()),
+            result => Err(Error::CommandFailed { command, result }),
+        }
+    }
+
+    fn wait_for_ack(
+        &self,
+        messages: &Subscription,
+        command: MavCmd,
+        timeout: Duration,
+    ) -> Result<COMMAND_ACK_DATA> {
+        messages.wait_for(timeout, "COMMAND_ACK", |header, msg| match msg {
+            MavMessage::COMMAND_ACK(data)
+                if header.component_id == self.component_id() && self.is_ack_for(data, command) =>
+            {
+                Some(data.clone())
+            }
+            _ => None,
+        })
+    }
+
+    /// Autopilots that predate the ACK target fields leave them at zero.
+    fn is_ack_for(&self, ack: &COMMAND_ACK_DATA, command: MavCmd) -> bool {
+        let station = self.station();
+        ack.command == command
+            && (ack.target_system == 0 || ack.target_system == station.system_id())
+            && (ack.target_component == 0 || ack.target_component == station.component_id())
+    }
+}
+
+/// One of param1 to param4, the params of a command or mission item that
+/// do not hold its position.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParamIndex {
+    Param1,
+    Param2,
+    Param3,
+    Param4,
+}
+
+/// A command whose params 5-7 are a position, such as MAV_CMD_DO_REPOSITION.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PositionCommand {
+    command: MavCmd,
+    frame: MavFrame,
+    params: [f32; 4],
+    /// `None` leaves the position to the vehicle.
+    location: Option<LatLon>,
+    altitude: f32,
+}
+
+impl PositionCommand {
+    pub fn new(command: MavCmd, frame: MavFrame) -> Self {
+        Self {
+            command,
+            frame,
+            params: [0.0; 4],
+            location: None,
+            altitude: 0.0,
+        }
+    }
+
+    /// Fly to a point in GUIDED mode at the default speed, keeping the current heading.
+    pub fn reposition(latitude: f64, longitude: f64, altitude: f32) -> Result<Self> {
+        Ok(Self::new(
+            MavCmd::MAV_CMD_DO_REPOSITION,
+            MavFrame::MAV_FRAME_GLOBAL_RELATIVE_ALT,
+        )
+        .param(ParamIndex::Param1, -1.0)
+        .param(ParamIndex::Param2, MAV_DO_REPOSITION_FLAGS_CHANGE_MODE)
+        .param(ParamIndex::Param4, f32::NAN)
+        .location(latitude, longitude)?
+        .altitude(altitude))
+    }
+
+    /// Climb to `altitude` metres above home from the current position.
+    pub fn takeoff(altitude: f32) -> Self {
+        Self::new(
+            MavCmd::MAV_CMD_NAV_TAKEOFF,
+            MavFrame::MAV_FRAME_GLOBAL_RELATIVE_ALT,
+        )
+        .param(ParamIndex::Param4, f32::NAN)
+        .altitude(altitude)
+    }
+
+    /// Move home to a point with an AMSL `altitude`.
+    pub fn set_home(latitude: f64, longitude: f64, altitude: f32) -> Result<Self> {
+        Ok(
+            Self::new(MavCmd::MAV_CMD_DO_SET_HOME, MavFrame::MAV_FRAME_GLOBAL)
+                .location(latitude, longitude)?
+                .altitude(altitude),
+        )
+    }
+
+    /// Move home to the vehicle's current position.
+    pub fn set_home_to_current() -> Self {
+        Self::new(MavCmd::MAV_CMD_DO_SET_HOME, MavFrame::MAV_FRAME_GLOBAL)
+            .param(ParamIndex::Param1, 1.0)
+    }
+
+    /// Sets one of param1 to param4; params 5-7 are the position.
+    pub fn param(mut self, index: ParamIndex, value: f32) -> Self {
+        self.params[index as usize] = value;
+        self
+    }
+
+    pub fn frame(mut self, frame: MavFrame) -> Self {
+        self.frame = frame;
+        self
+    }
+
+    /// Latitude and longitude in degrees; fails with
+    /// [`Error::InvalidPosition`] unless they are [valid](LatLon::is_valid).
+    pub fn location(mut self, latitude: f64, longitude: f64) -> Result<Self> {
+        let location = LatLon::new(latitude, longitude);
+        location.to_e7()?;
+        self.location = Some(location);
+        Ok(self)
+    }
+
+    pub fn altitude(mut self, altitude: f32) -> Self {
+        self.altitude = altitude;
+        self
+    }
+
+    fn to_command_int(&self, vehicle: &Vehicle) -> Result<COMMAND_INT_DATA> {
+        // INT32_MAX marks x/y as unused in COMMAND_INT.
+        let (x, y) = match self.location {
+            Some(location) => location.to_e7()?,
+            None => (i32::MAX, i32::MAX),
+        };
+        Ok(COMMAND_INT_DATA {
+            target_system: vehicle.system_id(),
+            target_component: vehicle.component_id(),
+            command: self.command,
+            frame: self.frame,
+            param1: self.params[0],
+            param2: self.params[1],
+            param3: self.params[2],
+            param4: self.params[3],
+            x,
+            y,
+            z: self.altitude,
+            current: 0,
+            autocontinue: 0,
+        })
+    }
+
+    fn to_command_long_params(&self) -> [f32; 7] {
+        let (lat, lon) = self.location.map_or((f32::NAN, f32::NAN), |location| {
+            (location.latitude as f32, location.longitude as f32)
+        });
+        [
+            self.params[0],
+            self.params[1],
+            self.params[2],
+            self.params[3],
+            lat,
+            lon,
+            self.altitude,
+        ]
+    }
+}
+
+/// MAV_DO_REPOSITION_FLAGS_CHANGE_MODE: switch to GUIDED before moving.
+const MAV_DO_REPOSITION_FLAGS_CHANGE_MODE: f32 = 1.0;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::{self, MockPeer, command_ack};
+
+    const ARM: MavCmd = MavCmd::MAV_CMD_COMPONENT_ARM_DISARM;
+
+    fn confirmations(peer: &MockPeer) -> Vec<u8> {
+        peer.sent()
+            .iter()
+            .filter_map(|msg| match msg {
+                MavMessage::COMMAND_LONG(data) => Some(data.confirmation),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn ignores_acks_for_other_commands_and_stations() {
+        let (vehicle, peer) = mock::vehicle(|msg| match msg {
+            MavMessage::COMMAND_LONG(_) => vec![
+                command_ack(MavCmd::MAV_CMD_NAV_LAND, MavResult::MAV_RESULT_ACCEPTED),
+                MavMessage::COMMAND_ACK(COMMAND_ACK_DATA {
+                    command: ARM,
+                    result: MavResult::MAV_RESULT_ACCEPTED,
+                    target_system: 42,
+                    ..Default::default()
+                }),
+                command_ack(ARM, MavResult::MAV_RESULT_DENIED),
+            ],
+            _ => Vec::new(),
+        });
+        let result = vehicle.send_command(ARM, [1.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0]);
+        assert!(matches!(
+            result,
+            Err(Error::CommandFailed {
+                command: ARM,
+                result: MavResult::MAV_RESULT_DENIED
+            })
+        ));
+        assert_eq!(confirmations(&peer), [0]);
+    }
+
+    #[test]
+    fn resends_with_an_incremented_confirmation() {
+        let (vehicle, peer) = mock::vehicle(|msg| match msg {
+            MavMessage::COMMAND_LONG(data) if data.confirmation == 1 => {
+                vec![command_ack(ARM, MavResult::MAV_RESULT_ACCEPTED)]
+            }
+            _ => Vec::new(),
+        });
+        vehicle.send_command(ARM, [1.0; 7]).unwrap();
+        assert_eq!(confirmations(&peer), [0, 1]);
+    }
+
+    fn in_progress(command: MavCmd, progress: u8) -> MavMessage {
+        MavMessage::COMMAND_ACK(COMMAND_ACK_DATA {
+            command,
+            result: MavResult::MAV_RESULT_IN_PROGRESS,
+            progress,
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn reports_progress_until_the_final_ack() {
+        let calibrate = MavCmd::MAV_CMD_PREFLIGHT_CALIBRATION;
+        let (vehicle, _peer) = mock::vehicle(move |msg| match msg {
+            MavMessage::COMMAND_LONG(_) => vec![
+                in_progress(calibrate, 10),
+                in_progress(calibrate, 60),
+                command_ack(calibrate, MavResult::MAV_RESULT_ACCEPTED),
+            ],
+            _ => Vec::new(),
+        });
+        let mut progress = Vec::new();
+        vehicle
+            .send_command_with_progress(calibrate, [0.0; 7], |percent| progress.push(percent))
+            .unwrap();
+        assert_eq!(progress, [10, 60]);
+    }
+
+    #[test]
+    fn cancel_ends_a_command_in_progress() {
+        let calibrate = MavCmd::MAV_CMD_PREFLIGHT_CALIBRATION;
+        let (vehicle, peer) = mock::vehicle(move |msg| match msg {
+            MavMessage::COMMAND_LONG(_) => vec![in_progress(calibrate, 255)],
+            MavMessage::COMMAND_CANCEL(data) if data.command == calibrate => {
+                vec![command_ack(calibrate, MavResult::MAV_RESULT_CANCELLED)]
+            }
+            _ => Vec::new(),
+        });
+        let canceller = vehicle.clone();
+        let result = vehicle.send_command_with_progress(calibrate, [0.0; 7], |_| {
+            canceller.cancel_command(calibrate).unwrap();
+        });
+        assert!(matches!(
+            result,
+            Err(Error::CommandFailed {
+                result: MavResult::MAV_RESULT_CANCELLED,
+                ..
+            })
+        ));
+        assert_eq!(confirmations(&peer), [0]);
+    }
+
+    #[test]
+    fn times_out_after_the_last_retry() {
+        let (vehicle, peer) = mock::vehicle(|_| Vec::new());
+        let result = vehicle.send_command(ARM, [1.0; 7]);
+        assert!(matches!(result, Err(Error::Timeout("COMMAND_ACK"))));
+        assert_eq!(confirmations(&peer), [0, 1, 2, 3]);
+    }
+
+    fn command_int(peer: &MockPeer) -> COMMAND_INT_DATA {
+        match &peer.sent()[0] {
+            MavMessage::COMMAND_INT(data) => data.clone(),
+            other => panic!("sent {other:?}"),
+        }
+    }
+
+    #[test]
+    fn sends_positions_as_command_int() {
+        let (vehicle, peer) = mock::vehicle(|msg| match msg {
+            MavMessage::COMMAND_INT(data) => {
+                vec![command_ack(data.command, MavResult::MAV_RESULT_ACCEPTED)]
+            }
+            _ => Vec::new(),
+        });
+        vehicle
+            .send_position_command(
+                &PositionCommand::reposition(-35.3612577, 149.165172, 20.0).unwrap(),
+            )
+            .unwrap();
+        let data = command_int(&peer);
+        assert_eq!((data.x, data.y, data.z), (-353612577, 1491651720, 20.0));
+        assert_eq!(data.frame, MavFrame::MAV_FRAME_GLOBAL_RELATIVE_ALT);
+        assert_eq!([data.param1, data.param2], [-1.0, 1.0]);
+        assert!(data.param4.is_nan());
+        assert_eq!(peer.sent().len(), 1);
+    }
+
+    #[test]
+    fn marks_a_missing_position_unused() {
+        let (vehicle, peer) = mock::vehicle(|msg| match msg {
+            MavMessage::COMMAND_INT(data) => {
+                vec![command_ack(data.command, MavResult::MAV_RESULT_ACCEPTED)]
+            }
+            _ => Vec::new(),
+        });
+        vehicle
+            .send_position_command(&PositionCommand::takeoff(10.0))
+            .unwrap();
+        let data = command_int(&peer);
+        assert_eq!((data.x, data.y), (i32::MAX, i32::MAX));
+    }
+
+    #[test]
+    fn rejects_invalid_positions() {
+        assert!(matches!(
+            PositionCommand::reposition(f64::NAN, 149.165172, 20.0),
+            Err(Error::InvalidPosition(_))
+        ));
+        assert!(matches!(
+            PositionCommand::takeoff(10.0).location(-35.36, 214.0),
+            Err(Error::InvalidPosition(_))
+        ));
+    }
+
+    #[test]
+    fn falls_back_to_command_long_when_unsupported() {
+        let (vehicle, peer) = mock::vehicle(|msg| match msg {
+            MavMessage::COMMAND_INT(data) => {
+                vec![command_ack(data.command, MavResult::MAV_RESULT_UNSUPPORTED)]
+            }
+            MavMessage::COMMAND_LONG(data) => {
+                vec![command_ack(data.command, MavResult::MAV_RESULT_ACCEPTED)]
+            }
+            _ => Vec::new(),
+        });
+        vehicle
+            .send_position_command(&PositionCommand::set_home(-35.25, 149.5, 584.0).unwrap())
+            .unwrap();
+        let sent = peer.sent();
+        assert_eq!(sent.len(), 2);
+        let MavMessage::COMMAND_LONG(data) = &sent[1] else {
+            panic!("sent {sent:?}");
+        };
+        assert_eq!(data.command, MavCmd::MAV_CMD_DO_SET_HOME);
+        assert_eq!(
+            [data.param5, data.param6, data.param7],
+            [-35.25, 149.5, 584.0]
+        );
+    }
+}
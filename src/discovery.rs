@@ -0,0 +1,104 @@
+use std::{
+    collections::BTreeMap,
+    time::{Duration, Instant},
+};
+
+use mavlink::{
+    MavHeader,
+    ardupilotmega::{HEARTBEAT_DATA, MavAutopilot, MavMessage, MavType},
+};
+
+use crate::{
+    error::{Error, Result},
+    station::GroundStation,
+    vehicle::Vehicle,
+};
+
+/// A system/component that announced itself with a HEARTBEAT.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DiscoveredSystem {
+    pub system_id: u8,
+    pub component_id: u8,
+    pub mavtype: MavType,
+    pub autopilot: MavAutopilot,
+}
+
+impl DiscoveredSystem {
+    fn from_heartbeat(header: &MavHeader, data: &HEARTBEAT_DATA) -> Self {
+        Self {
+            system_id: header.system_id,
+            component_id: header.component_id,
+            mavtype: data.mavtype,
+            autopilot: data.autopilot,
+        }
+    }
+
+    /// False for ground stations, gimbals, cameras and other peripherals
+    /// that share the link with the flight controller.
+    pub fn is_autopilot(&self) -> bool {
+        self.autopilot != MavAutopilot::MAV_AUTOPILOT_INVALID
+            && !matches!(
+                self.mavtype,
+                MavType::MAV_TYPE_GCS
+                    | MavType::MAV_TYPE_ONBOARD_CONTROLLER
+                    | MavType::MAV_TYPE_GIMBAL
+                    | MavType::MAV_TYPE_CAMERA
+                    | MavType::MAV_TYPE_ADSB
+                    | MavType::MAV_TYPE_ANTENNA_TRACKER
+                    | MavType::MAV_TYPE_CHARGING_STATION
+                    | MavType::MAV_TYPE_FLARM
+                    | MavType::MAV_TYPE_SERVO
+                    | MavType::MAV_TYPE_ODID
+                    | MavType::MAV_TYPE_BATTERY
+                    | MavType::MAV_TYPE_PARACHUTE
+                    | MavType::MAV_TYPE_LOG
+                    | MavType::MAV_TYPE_OSD
+                    | MavType::MAV_TYPE_IMU
+                    | MavType::MAV_TYPE_GPS
+                    | MavType::MAV_TYPE_WINCH
+                    | MavType::MAV_TYPE_ILLUMINATOR
+            )
+    }
+}
+
+impl GroundStation {
+    /// Listens to HEARTBEATs for `listen_for` and returns every autopilot heard,
+    /// ordered by system and component id.
+    pub fn discover(&self, listen_for: Duration) -> Result<Vec<DiscoveredSystem>> {
+        let messages = self.subscribe();
+        let deadline = Instant::now() + listen_for;
+        let mut discovered = BTreeMap::new();
+        loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            let Some((header, msg)) = messages.recv_timeout(remaining)? else {
+                break;
+            };
+            if let MavMessage::HEARTBEAT(data) = msg {
+                let system = DiscoveredSystem::from_heartbeat(&header, &data);
+                if system.is_autopilot() {
+                    discovered.insert((header.system_id, header.component_id), system);
+                }
+            }
+        }
+        if discovered.is_empty() {
+            return Err(Error::Timeout("autopilot heartbeat"));
+        }
+        Ok(discovered.into_values().collect())
+    }
+
+    /// Targets the first autopilot that sends a HEARTBEAT.
+    pub fn wait_for_vehicle(&self, timeout: Duration) -> Result<Vehicle> {
+        let system = self
+            .subscribe()
+            .wait_for(timeout, "autopilot heartbeat", |header, msg| match msg {
+                MavMessage::HEARTBEAT(data) => Some(DiscoveredSystem::from_heartbeat(header, data))
+                    .filter(DiscoveredSystem::is_autopilot),
+                _ => None,
+            })?;
+        Ok(Vehicle::new(
+            self.clone(),
+            system.system_id,
+            system.component_id,
+        ))
+    }
+}
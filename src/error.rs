@@ -0,0 +1,160 @@
+use std::fmt;
+
+use mavlink::{
+    ardupilotmega::{MavCmd, MavMissionResult, MavResult},
+    error::MessageWriteError,
+};
+
+use crate::{
+    geo::LatLon,
+    mode::FlightMode,
+    param::{ParamId, ParamValue},
+};
+
+#[derive(Debug)]
+pub enum Error {
+    /// The connection to the MAVLink endpoint could not be opened.
+    Connect(std::io::Error),
+    /// A message could not be written to the connection.
+    Send(MessageWriteError),
+    /// A file could not be read or written.
+    Io(std::io::Error),
+    /// Nothing matching arrived before the deadline; holds what was awaited.
+    Timeout(&'static str),
+    /// The receive thread stopped, usually because the link was closed.
+    Disconnected,
+    /// The vehicle answered a command with anything but MAV_RESULT_ACCEPTED.
+    CommandFailed { command: MavCmd, result: MavResult },
+    /// The vehicle ended a mission transaction with a NACK.
+    MissionFailed(MavMissionResult),
+    /// Mission, fence or rally items that do not form a valid list.
+    InvalidMission(String),
+    /// A latitude or longitude that is not finite or lies outside ±90° or ±180°.
+    InvalidPosition(LatLon),
+    /// A flight mode name or number that the vehicle's mode table lacks.
+    InvalidMode(String),
+    /// The vehicle accepted a mode change but its HEARTBEAT reports another mode.
+    ModeReverted {
+        requested: FlightMode,
+        reported: Option<FlightMode>,
+    },
+    /// A parameter name or value the vehicle cannot accept.
+    InvalidParam(String),
+    /// The vehicle echoed a different value than the one written.
+    ParamMismatch {
+        id: ParamId,
+        requested: ParamValue,
+        echoed: ParamValue,
+    },
+    /// A value outside the range the parameter documentation allows.
+    OutOfRange {
+        id: ParamId,
+        value: f64,
+        min: f64,
+        max: f64,
+    },
+    /// A parameter metadata file that could not be parsed.
+    InvalidParamMetadata(String),
+    /// A parameter file line that is not in the expected format.
+    InvalidParamFile { line: usize, message: String },
+    /// A mission file line that is not in the expected format.
+    InvalidMissionFile { line: usize, message: String },
+    /// A QGroundControl plan file that could not be read.
+    InvalidPlan(String),
+    /// Parameters from a bulk apply that could not be written, with the reason for each.
+    ParamsNotApplied(Vec<(ParamId, Error)>),
+    /// The vehicle refused to arm or disarm; `reasons` holds the failed checks it reported.
+    ArmingRejected {
+        arm: bool,
+        result: MavResult,
+        reasons: Vec<String>,
+    },
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Connect(e) => write!(f, "failed to connect: {e}"),
+            Error::Send(e) => write!(f, "failed to send message: {e}"),
+            Error::Io(e) => write!(f, "I/O error: {e}"),
+            Error::Timeout(waiting_for) => write!(f, "timed out waiting for {waiting_for}"),
+            Error::Disconnected => write!(f, "connection closed"),
+            Error::CommandFailed { command, result } => write!(f, "{command:?} failed: {result:?}"),
+            Error::MissionFailed(result) => write!(f, "mission rejected: {result:?}"),
+            Error::InvalidMission(message) => write!(f, "invalid mission: {message}"),
+            Error::InvalidPosition(position) => write!(
+                f,
+                "invalid position {position}: latitude must be within ±90° and longitude within ±180°"
+            ),
+            Error::InvalidMode(message) => write!(f, "invalid flight mode: {message}"),
+            Error::ModeReverted {
+                requested,
+                reported: Some(reported),
+            } => write!(f, "vehicle accepted {requested} but reverted to {reported}"),
+            Error::ModeReverted {
+                requested,
+                reported: None,
+            } => write!(
+                f,
+                "vehicle accepted {requested} but reverted to an unknown mode"
+            ),
+            Error::InvalidParam(message) => write!(f, "invalid parameter: {message}"),
+            Error::ParamMismatch {
+                id,
+                requested,
+                echoed,
+            } => write!(f, "{id} was set to {echoed} instead of {requested}"),
+            Error::OutOfRange {
+                id,
+                value,
+                min,
+                max,
+            } => write!(
+                f,
+                "{value} is outside the range {min} to {max} of {id}; force the write to set it anyway"
+            ),
+            Error::InvalidParamMetadata(message) => {
+                write!(f, "invalid parameter metadata: {message}")
+            }
+            Error::InvalidParamFile { line, message } => {
+                write!(f, "invalid parameter file, line {line}: {message}")
+            }
+            Error::InvalidMissionFile { line, message } => {
+                write!(f, "invalid mission file, line {line}: {message}")
+            }
+            Error::InvalidPlan(message) => write!(f, "invalid plan file: {message}"),
+            Error::ParamsNotApplied(failures) => {
+                write!(f, "{} parameters not applied", failures.len())?;
+                for (id, error) in failures {
+                    write!(f, "\n  {id}: {error}")?;
+                }
+                Ok(())
+            }
+            Error::ArmingRejected {
+                arm,
+                result,
+                reasons,
+            } => {
+                let action = if *arm { "arming" } else { "disarming" };
+                write!(f, "{action} rejected: {result:?}")?;
+                for reason in reasons {
+                    write!(f, "\n  {reason}")?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Connect(e) => Some(e),
+            Error::Send(e) => Some(e),
+            Error::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,289 @@
+use mavlink::ardupilotmega::{MavCmd, MavFrame, MavMissionType};
+
+use crate::{
+    command::ParamIndex,
+    error::{Error, Result},
+    geo::LatLon,
+    mission::MissionItem,
+    vehicle::Vehicle,
+};
+
+/// One area of a geofence.
+#[derive(Debug, Clone, PartialEq)]
+pub enum FenceZone {
+    /// The vehicle must stay inside the polygon.
+    InclusionPolygon(Vec<LatLon>),
+    /// The vehicle must stay outside the polygon.
+    ExclusionPolygon(Vec<LatLon>),
+    InclusionCircle {
+        center: LatLon,
+        radius: f32,
+    },
+    ExclusionCircle {
+        center: LatLon,
+        radius: f32,
+    },
+}
+
+/// A geofence, stored on the vehicle as MAV_MISSION_TYPE_FENCE items.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Fence {
+    pub zones: Vec<FenceZone>,
+    /// Where the vehicle goes after a breach, with its altitude in metres
+    /// above home.
+    pub return_point: Option<(LatLon, f32)>,
+}
+
+impl Fence {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a polygon the vehicle must stay inside; needs at least three vertices.
+    pub fn inclusion_polygon(
+        mut self,
+        vertices: impl IntoIterator<Item = impl Into<LatLon>>,
+    ) -> Self {
+        self.zones.push(FenceZone::InclusionPolygon(
+            vertices.into_iter().map(Into::into).collect(),
+        ));
+        self
+    }
+
+    /// Adds a polygon the vehicle must stay outside; needs at least three vertices.
+    pub fn exclusion_polygon(
+        mut self,
+        vertices: impl IntoIterator<Item = impl Into<LatLon>>,
+    ) -> Self {
+        self.zones.push(FenceZone::ExclusionPolygon(
+            vertices.into_iter().map(Into::into).collect(),
+        ));
+        self
+    }
+
+    /// Adds a circle of `radius` metres the vehicle must stay inside.
+    pub fn inclusion_circle(mut self, latitude: f64, longitude: f64, radius: f32) -> Self {
+        self.zones.push(FenceZone::InclusionCircle {
+            center: LatLon::new(latitude, longitude),
+            radius,
+        });
+        self
+    }
+
+    /// Adds a circle of `radius` metres the vehicle must stay outside.
+    pub fn exclusion_circle(mut self, latitude: f64, longitude: f64, radius: f32) -> Self {
+        self.zones.push(FenceZone::ExclusionCircle {
+            center: LatLon::new(latitude, longitude),
+            radius,
+        });
+        self
+    }
+
+    pub fn return_point(mut self, latitude: f64, longitude: f64, altitude: f32) -> Self {
+        self.return_point = Some((LatLon::new(latitude, longitude), altitude));
+        self
+    }
+
+    /// The fence as mission items: the return point first, then each
+    /// polygon as one item per vertex carrying the vertex count.
+    pub fn to_items(&self) -> Result<Vec<MissionItem>> {
+        let mut items = Vec::new();
+        if let Some((position, altitude)) = self.return_point {
+            items.push(
+                fence_item(MavCmd::MAV_CMD_NAV_FENCE_RETURN_POINT, position)?
+                    .frame(MavFrame::MAV_FRAME_GLOBAL_RELATIVE_ALT)
+                    .altitude(altitude),
+            );
+        }
+        for zone in &self.zones {
+            match zone {
+                FenceZone::InclusionPolygon(vertices) | FenceZone::ExclusionPolygon(vertices) => {
+                    if vertices.len() < 3 {
+                        return Err(Error::InvalidMission(format!(
+                            "a fence polygon needs at least 3 vertices, not {}",
+                            vertices.len()
+                        )));
+                    }
+                    let command = match zone {
+                        FenceZone::InclusionPolygon(_) => {
+                            MavCmd::MAV_CMD_NAV_FENCE_POLYGON_VERTEX_INCLUSION
+                        }
+                        _ => MavCmd::MAV_CMD_NAV_FENCE_POLYGON_VERTEX_EXCLUSION,
+                    };
+                    for &vertex in vertices {
+                        items.push(
+                            fence_item(command, vertex)?
+                                .param(ParamIndex::Param1, vertices.len() as f32),
+                        );
+                    }
+                }
+                FenceZone::InclusionCircle { center, radius } => items.push(
+                    fence_item(MavCmd::MAV_CMD_NAV_FENCE_CIRCLE_INCLUSION, *center)?
+                        .param(ParamIndex::Param1, *radius),
+                ),
+                FenceZone::ExclusionCircle { center, radius } => items.push(
+                    fence_item(MavCmd::MAV_CMD_NAV_FENCE_CIRCLE_EXCLUSION, *center)?
+                        .param(ParamIndex::Param1, *radius),
+                ),
+            }
+        }
+        Ok(items)
+    }
+
+    /// Rebuilds a fence from downloaded MAV_MISSION_TYPE_FENCE items.
+    pub fn from_items(items: &[MissionItem]) -> Result<Self> {
+        let mut fence = Self::new();
+        let mut seq = 0;
+        while let Some(item) = items.get(seq) {
+            let position = LatLon::from_e7(item.x, item.y);
+            match item.command {
+                MavCmd::MAV_CMD_NAV_FENCE_RETURN_POINT => {
+                    fence.return_point = Some((position, item.z));
+                }
+                MavCmd::MAV_CMD_NAV_FENCE_POLYGON_VERTEX_INCLUSION
+                | MavCmd::MAV_CMD_NAV_FENCE_POLYGON_VERTEX_EXCLUSION => {
+                    let count = item.params[0] as usize;
+                    let vertices = seq
+                        .checked_add(count)
+                        .and_then(|end| items.get(seq..end))
+                        .filter(|vertices| {
+                            count >= 3
+                                && vertices.iter().all(|vertex| vertex.command == item.command)
+                        });
+                    let Some(vertices) = vertices else {
+                        return Err(Error::InvalidMission(format!(
+                            "fence polygon at item {seq} lacks some of its {count} vertices"
+                        )));
+                    };
+                    let vertices = vertices
+                        .iter()
+                        .map(|vertex| LatLon::from_e7(vertex.x, vertex.y))
+                        .collect();
+                    fence.zones.push(
+                        if item.command == MavCmd::MAV_CMD_NAV_FENCE_POLYGON_VERTEX_INCLUSION {
+                            FenceZone::InclusionPolygon(vertices)
+                        } else {
+                            FenceZone::ExclusionPolygon(vertices)
+                        },
+                    );
+                    seq += count;
+                    continue;
+                }
+                MavCmd::MAV_CMD_NAV_FENCE_CIRCLE_INCLUSION => {
+                    fence.zones.push(FenceZone::InclusionCircle {
+                        center: position,
+                        radius: item.params[0],
+                    });
+                }
+                MavCmd::MAV_CMD_NAV_FENCE_CIRCLE_EXCLUSION => {
+                    fence.zones.push(FenceZone::ExclusionCircle {
+                        center: position,
+                        radius: item.params[0],
+                    });
+                }
+                command => {
+                    return Err(Error::InvalidMission(format!(
+                        "fence item {seq} is {command:?}"
+                    )));
+                }
+            }
+            seq += 1;
+        }
+        Ok(fence)
+    }
+}
+
+fn fence_item(command: MavCmd, position: LatLon) -> Result<MissionItem> {
+    MissionItem::new(command, MavFrame::MAV_FRAME_GLOBAL)
+        .location(position.latitude, position.longitude)
+}
+
+impl Vehicle {
+    /// Replaces the vehicle's geofence.
+    pub fn upload_fence(&self, fence: &Fence) -> Result<()> {
+        self.upload_items(MavMissionType::MAV_MISSION_TYPE_FENCE, &fence.to_items()?)
+    }
+
+    pub fn download_fence(&self) -> Result<Fence> {
+        Fence::from_items(&self.download_items(MavMissionType::MAV_MISSION_TYPE_FENCE)?)
+    }
+
+    pub fn clear_fence(&self) -> Result<()> {
+        self.clear_items(MavMissionType::MAV_MISSION_TYPE_FENCE)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> Fence {
+        Fence::new()
+            .return_point(-35.3632621, 149.1652374, 40.0)
+            .inclusion_polygon([(-35.1, 149.1), (-35.2, 149.1), (-35.2, 149.2)])
+            .exclusion_circle(-35.36, 149.16, 30.5)
+            .exclusion_polygon([
+                (-35.3, 149.3),
+                (-35.4, 149.3),
+                (-35.4, 149.4),
+                (-35.3, 149.4),
+            ])
+            .inclusion_circle(-35.0, 149.0, 500.0)
+    }
+
+    #[test]
+    fn round_trips_through_items() {
+        let fence = sample();
+        let items = fence.to_items().unwrap();
+        assert_eq!(items.len(), 1 + 3 + 1 + 4 + 1);
+        assert_eq!(items[0].command, MavCmd::MAV_CMD_NAV_FENCE_RETURN_POINT);
+        assert_eq!(items[1].params[0], 3.0);
+        assert_eq!(Fence::from_items(&items).unwrap(), fence);
+    }
+
+    #[test]
+    fn rejects_polygons_with_too_few_vertices() {
+        let fence = Fence::new().inclusion_polygon([(-35.1, 149.1), (-35.2, 149.1)]);
+        assert!(matches!(fence.to_items(), Err(Error::InvalidMission(_))));
+    }
+
+    #[test]
+    fn rejects_invalid_positions() {
+        let fence =
+            Fence::new().inclusion_polygon([(-35.1, 149.1), (f64::NAN, 149.1), (-35.2, 149.2)]);
+        assert!(matches!(fence.to_items(), Err(Error::InvalidPosition(_))));
+        let fence = Fence::new().exclusion_circle(-353.6, 149.16, 30.5);
+        assert!(matches!(fence.to_items(), Err(Error::InvalidPosition(_))));
+        let fence = Fence::new().return_point(-35.36, 1491.6, 40.0);
+        assert!(matches!(fence.to_items(), Err(Error::InvalidPosition(_))));
+    }
+
+    #[test]
+    fn rejects_bad_vertex_counts() {
+        let mut items = sample().to_items().unwrap();
+        items[1].params[0] = 1e30;
+        assert!(matches!(
+            Fence::from_items(&items),
+            Err(Error::InvalidMission(_))
+        ));
+        items[1].params[0] = 5.0;
+        assert!(matches!(
+            Fence::from_items(&items),
+            Err(Error::InvalidMission(_))
+        ));
+        items[1].params[0] = 2.0;
+        assert!(matches!(
+            Fence::from_items(&items),
+            Err(Error::InvalidMission(_))
+        ));
+    }
+
+    #[test]
+    fn rejects_other_commands() {
+        let items = [MissionItem::waypoint(-35.0, 149.0, 10.0).unwrap()];
+        assert!(matches!(
+            Fence::from_items(&items),
+            Err(Error::InvalidMission(_))
+        ));
+    }
+}
@@ -0,0 +1,138 @@
+use std::{fmt, time::Duration};
+
+use mavlink::ardupilotmega::GLOBAL_POSITION_INT_DATA;
+
+use crate::error::{Error, Result};
+
+/// Scale of the degE7 integers MAVLink uses for latitude and longitude.
+const DEG_E7: f64 = 1e7;
+
+/// A latitude and longitude in degrees.
+///
+/// Kept in f64 so that degE7 integers from MAVLink convert back and forth
+/// without loss; f32 only holds about 7 significant digits, which is metres
+/// of error at this scale.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct LatLon {
+    pub latitude: f64,
+    pub longitude: f64,
+}
+
+impl LatLon {
+    pub fn new(latitude: f64, longitude: f64) -> Self {
+        Self {
+            latitude,
+            longitude,
+        }
+    }
+
+    /// From MAVLink's degE7 integers, such as GLOBAL_POSITION_INT lat/lon
+    /// or MISSION_ITEM_INT x/y.
+    pub fn from_e7(latitude: i32, longitude: i32) -> Self {
+        Self::new(latitude as f64 / DEG_E7, longitude as f64 / DEG_E7)
+    }
+
+    /// Whether the latitude is within ±90° and the longitude within ±180°;
+    /// false for NaN and infinities.
+    pub fn is_valid(&self) -> bool {
+        (-90.0..=90.0).contains(&self.latitude) && (-180.0..=180.0).contains(&self.longitude)
+    }
+
+    /// To degE7 integers, rounded to the nearest 1e-7 degree. Positions that
+    /// are not [valid](LatLon::is_valid) are [`Error::InvalidPosition`].
+    pub fn to_e7(self) -> Result<(i32, i32)> {
+        if !self.is_valid() {
+            return Err(Error::InvalidPosition(self));
+        }
+        Ok((
+            (self.latitude * DEG_E7).round() as i32,
+            (self.longitude * DEG_E7).round() as i32,
+        ))
+    }
+}
+
+impl From<(f64, f64)> for LatLon {
+    fn from((latitude, longitude): (f64, f64)) -> Self {
+        Self::new(latitude, longitude)
+    }
+}
+
+impl fmt::Display for LatLon {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}, {}", self.latitude, self.longitude)
+    }
+}
+
+/// GLOBAL_POSITION_INT in degrees, metres and metres per second.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GlobalPosition {
+    /// Time since the autopilot booted.
+    pub time_boot: Duration,
+    pub position: LatLon,
+    /// Metres above mean sea level.
+    pub altitude: f32,
+    /// Metres above home.
+    pub relative_altitude: f32,
+    /// North, east and down speed.
+    pub velocity: [f32; 3],
+    /// Degrees from north, if the vehicle knows it.
+    pub heading: Option<f32>,
+}
+
+impl From<&GLOBAL_POSITION_INT_DATA> for GlobalPosition {
+    fn from(data: &GLOBAL_POSITION_INT_DATA) -> Self {
+        Self {
+            time_boot: Duration::from_millis(data.time_boot_ms.into()),
+            position: LatLon::from_e7(data.lat, data.lon),
+            altitude: data.alt as f32 / 1000.0,
+            relative_altitude: data.relative_alt as f32 / 1000.0,
+            velocity: [data.vx, data.vy, data.vz].map(|v| v as f32 / 100.0),
+            heading: (data.hdg != u16::MAX).then(|| data.hdg as f32 / 100.0),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips_through_e7() {
+        let position = LatLon::from_e7(-353612577, 1491651720);
+        assert_eq!(position, LatLon::new(-35.3612577, 149.165172));
+        assert_eq!(position.to_e7().unwrap(), (-353612577, 1491651720));
+        assert_eq!(
+            LatLon::new(-90.0, 180.0).to_e7().unwrap(),
+            (-900000000, 1800000000)
+        );
+    }
+
+    #[test]
+    fn rounds_to_the_nearest_e7() {
+        assert_eq!(
+            LatLon::new(-35.36125769, 149.16517199).to_e7().unwrap(),
+            (-353612577, 1491651720)
+        );
+        assert_eq!(
+            LatLon::new(0.00000004, -0.00000006).to_e7().unwrap(),
+            (0, -1)
+        );
+    }
+
+    #[test]
+    fn rejects_non_finite_and_out_of_range_positions() {
+        for position in [
+            LatLon::new(f64::NAN, 149.0),
+            LatLon::new(-35.0, f64::INFINITY),
+            LatLon::new(90.5, 149.0),
+            LatLon::new(-35.0, -180.5),
+            LatLon::new(353.6, 1491.6),
+        ] {
+            assert!(!position.is_valid());
+            assert!(matches!(
+                position.to_e7(),
+                Err(Error::InvalidPosition(p)) if p.longitude == position.longitude
+            ));
+        }
+    }
+}
@@ -0,0 +1,38 @@
+pub mod arming;
+pub mod command;
+pub mod discovery;
+pub mod error;
+pub mod fence;
+pub mod geo;
+pub mod mission;
+pub mod mission_file;
+#[cfg(test)]
+mod mock;
+pub mod mode;
+pub mod param;
+pub mod param_file;
+pub mod param_meta;
+pub mod plan_file;
+pub mod rally;
+pub mod route;
+pub mod station;
+pub mod track;
+pub mod vehicle;
+
+pub use command::{ParamIndex, PositionCommand};
+pub use discovery::DiscoveredSystem;
+pub use error::{Error, Result};
+pub use fence::{Fence, FenceZone};
+pub use geo::{GlobalPosition, LatLon};
+pub use mission::MissionItem;
+pub use mission_file::WaypointFile;
+pub use mode::{CopterMode, FlightMode, ModeFamily, PlaneMode, Px4Mode, RoverMode, SubMode};
+pub use param::{Param, ParamEncoding, ParamId, ParamTable, ParamValue};
+pub use param_file::{DIFF_TOLERANCE, ParamChange, ParamFile, ParamFileFormat};
+pub use param_meta::{ParamMeta, ParamMetaDb};
+pub use plan_file::Plan;
+pub use rally::RallyPoint;
+pub use route::{Altitude, Route, RoutePoint};
+pub use station::{Connection, GroundStation, Subscription};
+pub use track::{Track, TrackPoint};
+pub use vehicle::Vehicle;
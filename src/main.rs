@@ -1,3 +1,475 @@
-fn main() {
-    println!("Hello, world!");
+use std::{
+    path::{Path, PathBuf},
+    process::ExitCode,
+    thread,
+    time::Duration,
+};
+
+use mavlink::ardupilotmega::{
+    HEARTBEAT_DATA, MavAutopilot, MavFrame, MavMessage, MavState, MavType,
+};
+use mavlink_rust_edu::{
+    DIFF_TOLERANCE, Error, FlightMode, GlobalPosition, GroundStation, MissionItem, ModeFamily,
+    Param, ParamChange, ParamFile, ParamId, ParamMetaDb, Plan, Route, Vehicle, WaypointFile,
+};
+
+const USAGE: &str = "\
+Usage: mavlink-rust-edu <CONNECTION> [--param-meta <apm.pdef.xml>] <COMMAND>
+
+Commands:
+  discover                   List the autopilots heard on the link
+  heartbeat                  Send a GCS heartbeat every second
+  watch                      Print heartbeat, attitude and position messages
+  params list                Download and print all parameters
+  params get <NAME>          Read one parameter
+  params set <NAME> <VALUE> [--force]
+                             Write one parameter; --force skips the range check
+  params save <FILE>         Download all parameters to a .parm or QGC .params file
+  params load <FILE>         Write the values in a parameter file that differ
+  params diff <FILE> [--push]
+                             Compare the vehicle with a parameter file and
+                             optionally write the values that changed
+  params diff <FILE> <FILE>  Compare two parameter files without connecting
+  arm [--force]              Arm the vehicle, optionally skipping pre-arm checks
+  disarm [--force]           Disarm the vehicle, optionally even in flight
+  mode <NAME>                Change flight mode, e.g. GUIDED or FBWA
+  mission upload [FILE]      Upload a QGC .waypoints or .plan file, or the demo mission
+  mission download [FILE]    Print the mission stored on the vehicle, or save it
+                             as a .waypoints file, a .kml or .gpx route or,
+                             with fence and rally points, a .plan file
+  mission clear              Remove the mission from the vehicle
+  mission current <SEQ>      Continue the mission from item SEQ
+  track <FILE> <SECONDS>     Record the vehicle's position to a GPX track
+
+CONNECTION is a mavlink address such as tcpout:127.0.0.1:14550
+
+--param-meta loads ArduPilot parameter documentation to describe parameters
+and to refuse values outside their documented range.
+
+Exit codes: 0 success, 1 error, 2 usage, 3 rejected by the vehicle, 4 timeout";
+
+const EXIT_ERROR: u8 = 1;
+const EXIT_USAGE: u8 = 2;
+const EXIT_REJECTED: u8 = 3;
+const EXIT_TIMEOUT: u8 = 4;
+
+const VEHICLE_TIMEOUT: Duration = Duration::from_secs(10);
+const DISCOVER_DURATION: Duration = Duration::from_secs(3);
+
+enum Command {
+    Discover,
+    Heartbeat,
+    Watch,
+    ParamsList,
+    ParamsGet(ParamId),
+    ParamsSet(ParamId, f64),
+    ParamsForceSet(ParamId, f64),
+    ParamsSave(PathBuf),
+    ParamsLoad(PathBuf),
+    ParamsDiff { path: PathBuf, push: bool },
+    ParamsDiffFiles(PathBuf, PathBuf),
+    Arm { force: bool },
+    Disarm { force: bool },
+    Mode(String),
+    MissionUpload(Option<PathBuf>),
+    MissionDownload(Option<PathBuf>),
+    MissionClear,
+    MissionCurrent(u16),
+    Track(PathBuf, Duration),
+}
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let Some((address, command)) = args.split_first() else {
+        eprintln!("{USAGE}");
+        return ExitCode::from(EXIT_USAGE);
+    };
+    let (param_meta, command) = match command {
+        [flag, path, command @ ..] if flag == "--param-meta" => {
+            (Some(PathBuf::from(path)), command)
+        }
+        command => (None, command),
+    };
+    let command = match parse_command(command) {
+        Ok(command) => command,
+        Err(message) => {
+            eprintln!("{message}\n\n{USAGE}");
+            return ExitCode::from(EXIT_USAGE);
+        }
+    };
+    match run(address, param_meta.as_deref(), command) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("error: {e}");
+            ExitCode::from(exit_code(&e))
+        }
+    }
+}
+
+fn parse_command(args: &[String]) -> Result<Command, String> {
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    let command = match args.as_slice() {
+        ["discover"] => Command::Discover,
+        ["heartbeat"] => Command::Heartbeat,
+        ["watch"] => Command::Watch,
+        ["params", "list"] => Command::ParamsList,
+        ["params", "get", name] => Command::ParamsGet(parse_param_id(name)?),
+        ["params", "set", name, value] => {
+            Command::ParamsSet(parse_param_id(name)?, parse_param_value(value)?)
+        }
+        ["params", "set", name, value, "--force"] => {
+            Command::ParamsForceSet(parse_param_id(name)?, parse_param_value(value)?)
+        }
+        ["params", "save", path] => Command::ParamsSave(PathBuf::from(path)),
+        ["params", "load", path] => Command::ParamsLoad(PathBuf::from(path)),
+        ["params", "diff", path] => Command::ParamsDiff {
+            path: PathBuf::from(path),
+            push: false,
+        },
+        ["params", "diff", path, "--push"] => Command::ParamsDiff {
+            path: PathBuf::from(path),
+            push: true,
+        },
+        ["params", "diff", from, to] => {
+            Command::ParamsDiffFiles(PathBuf::from(from), PathBuf::from(to))
+        }
+        ["arm"] => Command::Arm { force: false },
+        ["arm", "--force"] => Command::Arm { force: true },
+        ["disarm"] => Command::Disarm { force: false },
+        ["disarm", "--force"] => Command::Disarm { force: true },
+        ["mode", name] => Command::Mode(name.to_string()),
+        ["mission", "upload"] => Command::MissionUpload(None),
+        ["mission", "upload", path] => Command::MissionUpload(Some(PathBuf::from(path))),
+        ["mission", "download"] => Command::MissionDownload(None),
+        ["mission", "download", path] => Command::MissionDownload(Some(PathBuf::from(path))),
+        ["mission", "clear"] => Command::MissionClear,
+        ["mission", "current", seq] => Command::MissionCurrent(
+            seq.parse()
+                .map_err(|_| format!("invalid mission item number {seq}"))?,
+        ),
+        ["track", path, seconds] => Command::Track(
+            PathBuf::from(path),
+            seconds
+                .parse()
+                .ok()
+                .and_then(|seconds| Duration::try_from_secs_f64(seconds).ok())
+                .ok_or_else(|| format!("invalid number of seconds {seconds}"))?,
+        ),
+        [] => return Err("missing command".to_string()),
+        _ => return Err(format!("unknown command: {}", args.join(" "))),
+    };
+    Ok(command)
+}
+
+/// Whether `path` ends in one of `extensions`, ignoring case.
+fn has_extension(path: &Path, extensions: &[&str]) -> bool {
+    path.extension().is_some_and(|extension| {
+        extensions
+            .iter()
+            .any(|candidate| extension.eq_ignore_ascii_case(candidate))
+    })
+}
+
+fn parse_param_id(name: &str) -> Result<ParamId, String> {
+    ParamId::new(name).map_err(|e| e.to_string())
+}
+
+fn parse_param_value(value: &str) -> Result<f64, String> {
+    value
+        .parse()
+        .map_err(|_| format!("invalid parameter value {value}"))
+}
+
+fn exit_code(error: &Error) -> u8 {
+    match error {
+        Error::CommandFailed { .. }
+        | Error::MissionFailed(_)
+        | Error::ModeReverted { .. }
+        | Error::ArmingRejected { .. }
+        | Error::ParamMismatch { .. }
+        | Error::ParamsNotApplied(_) => EXIT_REJECTED,
+        Error::Timeout(_) => EXIT_TIMEOUT,
+        Error::InvalidMode(_)
+        | Error::InvalidParam(_)
+        | Error::InvalidParamFile { .. }
+        | Error::InvalidMissionFile { .. }
+        | Error::InvalidPlan(_)
+        | Error::OutOfRange { .. }
+        | Error::InvalidMission(_) => EXIT_USAGE,
+        _ => EXIT_ERROR,
+    }
+}
+
+fn run(address: &str, param_meta: Option<&Path>, command: Command) -> mavlink_rust_edu::Result<()> {
+    if let Command::ParamsDiffFiles(from, to) = &command {
+        print_changes(&ParamFile::load(from)?.diff(&ParamFile::load(to)?, DIFF_TOLERANCE));
+        return Ok(());
+    }
+    let station = GroundStation::connect(address)?;
+    match command {
+        Command::Discover => {
+            for system in station.discover(DISCOVER_DURATION)? {
+                println!(
+                    "{}\t{}\t{:?}\t{:?}",
+                    system.system_id, system.component_id, system.mavtype, system.autopilot
+                );
+            }
+            Ok(())
+        }
+        Command::Heartbeat => send_heartbeats(&station),
+        Command::Watch => watch(&station),
+        command => {
+            let mut vehicle = station.wait_for_vehicle(VEHICLE_TIMEOUT)?;
+            if let Some(path) = param_meta {
+                let db = load_param_meta(&vehicle, path)?;
+                vehicle = vehicle.with_param_metadata(db);
+            }
+            run_vehicle_command(&vehicle, command)
+        }
+    }
+}
+
+/// Picks the vehicle section of `apm.pdef.xml` from the vehicle's HEARTBEAT.
+fn load_param_meta(vehicle: &Vehicle, path: &Path) -> mavlink_rust_edu::Result<ParamMetaDb> {
+    let heartbeat = vehicle.wait_for_heartbeat(VEHICLE_TIMEOUT)?;
+    let section = match ModeFamily::from_heartbeat(&heartbeat) {
+        Some(ModeFamily::Copter) => "ArduCopter",
+        Some(ModeFamily::Plane) => "ArduPlane",
+        Some(ModeFamily::Rover) => "Rover",
+        Some(ModeFamily::Sub) => "ArduSub",
+        Some(ModeFamily::Px4) | None => {
+            return Err(Error::InvalidParamMetadata(format!(
+                "no ArduPilot parameter documentation for {:?} on {:?}",
+                heartbeat.mavtype, heartbeat.autopilot
+            )));
+        }
+    };
+    ParamMetaDb::load(path, section)
+}
+
+fn run_vehicle_command(vehicle: &Vehicle, command: Command) -> mavlink_rust_edu::Result<()> {
+    match command {
+        Command::ParamsList => {
+            let params = vehicle.list_params_with_progress(|fraction| {
+                eprint!("\rDownloading parameters: {:3.0}%", fraction * 100.0);
+            })?;
+            eprintln!();
+            for param in &params {
+                print_param(vehicle, param);
+            }
+            for index in params.skipped() {
+                eprintln!("warning: parameter {index} has an invalid name and was skipped");
+            }
+        }
+        Command::ParamsGet(id) => print_param(vehicle, &vehicle.read_param(&id)?),
+        Command::ParamsSet(id, value) => print_param(vehicle, &vehicle.set_param(&id, value)?),
+        Command::ParamsForceSet(id, value) => {
+            print_param(vehicle, &vehicle.force_set_param(&id, value)?)
+        }
+        Command::ParamsSave(path) => {
+            let params = vehicle.list_params()?;
+            params.save(&path)?;
+            println!("Saved {} parameters to {}", params.len(), path.display());
+        }
+        Command::ParamsLoad(path) => {
+            let file = ParamFile::load(&path)?;
+            let written = vehicle.apply_params(&file)?;
+            println!("Wrote {written} of {} parameters", file.len());
+        }
+        Command::ParamsDiff { path, push } => {
+            let params = vehicle.list_params()?;
+            let changes = ParamFile::from(&params).diff(&ParamFile::load(&path)?, DIFF_TOLERANCE);
+            print_changes(&changes);
+            if push {
+                let mut changed = ParamFile::default();
+                for change in &changes {
+                    if let ParamChange::Changed { id, to, .. } = change {
+                        changed.insert(id.clone(), *to);
+                    }
+                }
+                let written = vehicle.apply_params_with_table(&params, &changed)?;
+                println!("Wrote {written} parameters");
+            }
+        }
+        Command::Arm { force: false } => vehicle.arm()?,
+        Command::Arm { force: true } => vehicle.force_arm()?,
+        Command::Disarm { force: false } => vehicle.disarm()?,
+        Command::Disarm { force: true } => vehicle.force_disarm()?,
+        Command::Mode(name) => {
+            let heartbeat = vehicle.wait_for_heartbeat(VEHICLE_TIMEOUT)?;
+            let family = ModeFamily::from_heartbeat(&heartbeat).ok_or_else(|| {
+                Error::InvalidMode(format!(
+                    "no mode table for {:?} on {:?}",
+                    heartbeat.mavtype, heartbeat.autopilot
+                ))
+            })?;
+            vehicle.set_mode(FlightMode::parse(family, &name)?)?
+        }
+        Command::MissionUpload(None) => vehicle.upload_mission(&demo_mission()?)?,
+        Command::MissionUpload(Some(path)) if has_extension(&path, &["plan"]) => {
+            let plan = Plan::load(&path)?;
+            vehicle.upload_mission(&plan.mission)?;
+            vehicle.upload_fence(&plan.fence)?;
+            vehicle.upload_rally_points(&plan.rally_points)?;
+            println!(
+                "Uploaded {} mission items, {} fence zones and {} rally points",
+                plan.mission.len(),
+                plan.fence.zones.len(),
+                plan.rally_points.len()
+            );
+        }
+        Command::MissionUpload(Some(path)) => {
+            let file = WaypointFile::load(&path)?;
+            vehicle.upload_mission(&file.items)?;
+            println!("Uploaded {} mission items", file.items.len());
+        }
+        Command::MissionDownload(Some(path)) if has_extension(&path, &["kml", "gpx"]) => {
+            let route = Route::from_mission(&vehicle.download_mission()?);
+            route.save(&path)?;
+            println!(
+                "Saved a route of {} points to {}",
+                route.points.len(),
+                path.display()
+            );
+        }
+        Command::MissionDownload(Some(path)) if has_extension(&path, &["plan"]) => {
+            let plan = Plan {
+                mission: vehicle.download_mission()?,
+                fence: vehicle.download_fence()?,
+                rally_points: vehicle.download_rally_points()?,
+            };
+            plan.save(&path)?;
+            println!(
+                "Saved the mission, fence and rally points to {}",
+                path.display()
+            );
+        }
+        Command::MissionDownload(Some(path)) => {
+            let file = WaypointFile::new(vehicle.download_mission()?);
+            file.save(&path)?;
+            println!(
+                "Saved {} mission items to {}",
+                file.items.len(),
+                path.display()
+            );
+        }
+        Command::MissionDownload(None) => {
+            for (seq, item) in vehicle.download_mission()?.iter().enumerate() {
+                let [param1, param2, param3, param4] = item.params;
+                println!(
+                    "{seq}\t{:?}\t{:?}\t{param1}\t{param2}\t{param3}\t{param4}\t{}\t{}\t{}",
+                    item.command, item.frame, item.x, item.y, item.z
+                );
+            }
+        }
+        Command::MissionClear => vehicle.clear_mission()?,
+        Command::MissionCurrent(seq) => vehicle.set_current_item(seq)?,
+        Command::Track(path, duration) => {
+            let track = vehicle.record_track(duration)?;
+            if track.link_lost {
+                eprintln!("warning: the connection closed before the recording time was up");
+            }
+            track.save(&path)?;
+            println!(
+                "Saved a track of {} positions to {}",
+                track.points.len(),
+                path.display()
+            );
+        }
+        Command::Discover | Command::Heartbeat | Command::Watch | Command::ParamsDiffFiles(..) => {
+            unreachable!()
+        }
+    }
+    Ok(())
+}
+
+fn send_heartbeats(station: &GroundStation) -> mavlink_rust_edu::Result<()> {
+    let heartbeat_message = MavMessage::HEARTBEAT(HEARTBEAT_DATA {
+        mavtype: MavType::MAV_TYPE_GCS,
+        autopilot: MavAutopilot::MAV_AUTOPILOT_INVALID,
+        system_status: MavState::MAV_STATE_ACTIVE,
+        ..Default::default()
+    });
+    loop {
+        station.send(&heartbeat_message)?;
+        thread::sleep(Duration::from_secs(1));
+    }
+}
+
+fn watch(station: &GroundStation) -> mavlink_rust_edu::Result<()> {
+    for (header, msg) in station.subscribe().iter() {
+        match msg {
+            MavMessage::HEARTBEAT(data) => {
+                let mode = FlightMode::from_heartbeat(&data).map_or_else(
+                    || format!("UNKNOWN({})", data.custom_mode),
+                    |m| m.to_string(),
+                );
+                println!(
+                    "[{}:{}] HEARTBEAT, type: {:?}, system status: {:?}, mode: {}",
+                    header.system_id, header.component_id, data.mavtype, data.system_status, mode
+                );
+            }
+            MavMessage::GLOBAL_POSITION_INT(data) => {
+                let position = GlobalPosition::from(&data);
+                println!(
+                    "[{}:{}] GLOBAL_POSITION_INT, lat: {:?}, lon: {:?}, alt: {:?}, relative_alt: {:?}",
+                    header.system_id,
+                    header.component_id,
+                    position.position.latitude,
+                    position.position.longitude,
+                    position.altitude,
+                    position.relative_altitude
+                );
+            }
+            MavMessage::ATTITUDE(data) => {
+                println!(
+                    "[{}:{}] ATTITUDE, roll: {:?}, pitch: {:?}, yaw: {:?}",
+                    header.system_id,
+                    header.component_id,
+                    data.roll.to_degrees(),
+                    data.pitch.to_degrees(),
+                    data.yaw.to_degrees(),
+                );
+            }
+            _ => {
+                // ignore other messages
+            }
+        }
+    }
+    Err(Error::Disconnected)
+}
+
+fn print_changes(changes: &[ParamChange]) {
+    for change in changes {
+        println!("{change}");
+    }
+}
+
+/// Adds units, the value's enum/bitmask names and the title when metadata is loaded.
+fn print_param(vehicle: &Vehicle, param: &Param) {
+    let Some(meta) = vehicle.param_metadata().and_then(|db| db.get(&param.id)) else {
+        println!("{}\t{}", param.id, param.value);
+        return;
+    };
+    let mut value = param.value.to_string();
+    if let Some(units) = &meta.units {
+        value = format!("{value} {units}");
+    }
+    if let Some(label) = meta.describe_value(param.value.as_f64()) {
+        value = format!("{value} ({label})");
+    }
+    println!("{}\t{}\t{}", param.id, value, meta.display_name);
+}
+
+/// Same route as `examples/mission.rs`: home, takeoff, then five waypoints.
+fn demo_mission() -> mavlink_rust_edu::Result<Vec<MissionItem>> {
+    Ok(vec![
+        MissionItem::waypoint(0.0, 0.0, 0.0)?.frame(MavFrame::MAV_FRAME_GLOBAL),
+        MissionItem::takeoff(50.0),
+        MissionItem::waypoint(-35.36125769, 149.16517199, 100.0)?,
+        MissionItem::waypoint(-35.36225769, 149.16617199, 100.0)?,
+        MissionItem::waypoint(-35.36325769, 149.16717199, 100.0)?,
+        MissionItem::waypoint(-35.36425769, 149.16817199, 100.0)?,
+        MissionItem::waypoint(-35.36525769, 149.16917199, 100.0)?,
+    ])
 }
@@ -0,0 +1,910 @@
+use std::time::Duration;
+
+use mavlink::ardupilotmega::{
+    MISSION_ACK_DATA, MISSION_CLEAR_ALL_DATA, MISSION_COUNT_DATA, MISSION_ITEM_DATA,
+    MISSION_ITEM_INT_DATA, MISSION_REQUEST_INT_DATA, MISSION_REQUEST_LIST_DATA,
+    MISSION_SET_CURRENT_DATA, MISSION_WRITE_PARTIAL_LIST_DATA, MavCmd, MavFrame, MavMessage,
+    MavMissionResult, MavMissionType, MavResult,
+};
+
+use crate::{
+    command::ParamIndex,
+    error::{Error, Result},
+    geo::LatLon,
+    station::Subscription,
+    vehicle::Vehicle,
+};
+
+const MISSION_TIMEOUT: Duration = Duration::from_secs(3);
+/// Resends of an unanswered MISSION_COUNT or item before giving up.
+const MISSION_RETRIES: u8 = 3;
+
+/// A mission item as stored on the vehicle; its sequence number is its
+/// position in the mission.
+///
+/// Built with the constructors for common commands, such as
+/// `MissionItem::waypoint(lat, lon, alt)?.hold(Duration::from_secs(5))`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MissionItem {
+    pub command: MavCmd,
+    pub frame: MavFrame,
+    /// param1 to param4, whose meaning depends on `command`.
+    pub params: [f32; 4],
+    /// Latitude in degE7 for global frames (see [`MissionItem::lat_lon`]),
+    /// metres * 1e4 for local ones.
+    pub x: i32,
+    /// Longitude in degE7 for global frames, metres * 1e4 for local ones.
+    pub y: i32,
+    pub z: f32,
+    /// Whether this is the item the vehicle is currently flying to.
+    pub current: bool,
+    /// Whether the vehicle moves on to the next item once this one is done.
+    pub autocontinue: bool,
+}
+
+impl MissionItem {
+    /// An item with all params and the position zeroed, which continues
+    /// automatically.
+    pub fn new(command: MavCmd, frame: MavFrame) -> Self {
+        Self {
+            command,
+            frame,
+            params: [0.0; 4],
+            x: 0,
+            y: 0,
+            z: 0.0,
+            current: false,
+            autocontinue: true,
+        }
+    }
+
+    /// Fly to a point `altitude` metres above home.
+    pub fn waypoint(latitude: f64, longitude: f64, altitude: f32) -> Result<Self> {
+        Ok(Self::new(
+            MavCmd::MAV_CMD_NAV_WAYPOINT,
+            MavFrame::MAV_FRAME_GLOBAL_RELATIVE_ALT,
+        )
+        .location(latitude, longitude)?
+        .altitude(altitude))
+    }
+
+    /// Climb to `altitude` metres above home from where the vehicle is.
+    pub fn takeoff(altitude: f32) -> Self {
+        Self::new(
+            MavCmd::MAV_CMD_NAV_TAKEOFF,
+            MavFrame::MAV_FRAME_GLOBAL_RELATIVE_ALT,
+        )
+        .altitude(altitude)
+    }
+
+    /// Circle a point for `time`.
+    pub fn loiter_time(
+        latitude: f64,
+        longitude: f64,
+        altitude: f32,
+        time: Duration,
+    ) -> Result<Self> {
+        Ok(Self::new(
+            MavCmd::MAV_CMD_NAV_LOITER_TIME,
+            MavFrame::MAV_FRAME_GLOBAL_RELATIVE_ALT,
+        )
+        .location(latitude, longitude)?
+        .altitude(altitude)
+        .param(ParamIndex::Param1, time.as_secs_f32()))
+    }
+
+    /// Land where the vehicle is; add [`MissionItem::location`] to land elsewhere.
+    pub fn land() -> Self {
+        Self::new(
+            MavCmd::MAV_CMD_NAV_LAND,
+            MavFrame::MAV_FRAME_GLOBAL_RELATIVE_ALT,
+        )
+    }
+
+    /// Return to the launch point.
+    pub fn rtl() -> Self {
+        Self::new(
+            MavCmd::MAV_CMD_NAV_RETURN_TO_LAUNCH,
+            MavFrame::MAV_FRAME_GLOBAL_RELATIVE_ALT,
+        )
+    }
+
+    /// Change the ground speed to `speed` m/s, leaving the throttle alone.
+    pub fn do_change_speed(speed: f32) -> Self {
+        Self::new(
+            MavCmd::MAV_CMD_DO_CHANGE_SPEED,
+            MavFrame::MAV_FRAME_GLOBAL_RELATIVE_ALT,
+        )
+        .param(ParamIndex::Param1, 1.0)
+        .param(ParamIndex::Param2, speed)
+        .param(ParamIndex::Param3, -1.0)
+    }
+
+    /// Go back to item `seq`, `repeat` more times.
+    pub fn do_jump(seq: u16, repeat: u16) -> Self {
+        Self::new(
+            MavCmd::MAV_CMD_DO_JUMP,
+            MavFrame::MAV_FRAME_GLOBAL_RELATIVE_ALT,
+        )
+        .param(ParamIndex::Param1, seq as f32)
+        .param(ParamIndex::Param2, repeat as f32)
+    }
+
+    /// Wait at a waypoint for `time` before moving on.
+    pub fn hold(self, time: Duration) -> Self {
+        self.param(ParamIndex::Param1, time.as_secs_f32())
+    }
+
+    /// Count a waypoint as reached within `radius` metres.
+    pub fn accept_radius(self, radius: f32) -> Self {
+        self.param(ParamIndex::Param2, radius)
+    }
+
+    /// Sets one of param1 to param4.
+    pub fn param(mut self, index: ParamIndex, value: f32) -> Self {
+        self.params[index as usize] = value;
+        self
+    }
+
+    pub fn frame(mut self, frame: MavFrame) -> Self {
+        self.frame = frame;
+        self
+    }
+
+    /// Latitude and longitude in degrees; fails with
+    /// [`Error::InvalidPosition`] unless they are [valid](LatLon::is_valid).
+    pub fn location(mut self, latitude: f64, longitude: f64) -> Result<Self> {
+        (self.x, self.y) = LatLon::new(latitude, longitude).to_e7()?;
+        Ok(self)
+    }
+
+    pub fn altitude(mut self, altitude: f32) -> Self {
+        self.z = altitude;
+        self
+    }
+
+    pub fn autocontinue(mut self, autocontinue: bool) -> Self {
+        self.autocontinue = autocontinue;
+        self
+    }
+
+    /// The latitude and longitude of an item in a global frame.
+    pub fn lat_lon(&self) -> Option<LatLon> {
+        is_global(self.frame).then(|| LatLon::from_e7(self.x, self.y))
+    }
+
+    /// The item as MISSION_ITEM_INT at position `seq`, without a target or
+    /// mission type.
+    pub fn to_mission_item_int(&self, seq: u16) -> MISSION_ITEM_INT_DATA {
+        MISSION_ITEM_INT_DATA {
+            param1: self.params[0],
+            param2: self.params[1],
+            param3: self.params[2],
+            param4: self.params[3],
+            x: self.x,
+            y: self.y,
+            z: self.z,
+            seq,
+            command: self.command,
+            frame: self.frame,
+            current: self.current as u8,
+            autocontinue: self.autocontinue as u8,
+            ..Default::default()
+        }
+    }
+}
+
+impl From<&MISSION_ITEM_INT_DATA> for MissionItem {
+    fn from(data: &MISSION_ITEM_INT_DATA) -> Self {
+        Self {
+            command: data.command,
+            frame: data.frame,
+            params: [data.param1, data.param2, data.param3, data.param4],
+            x: data.x,
+            y: data.y,
+            z: data.z,
+            current: data.current != 0,
+            autocontinue: data.autocontinue != 0,
+        }
+    }
+}
+
+impl Vehicle {
+    /// Uploads `items`, replacing the vehicle's mission.
+    ///
+    /// Sends MISSION_COUNT and answers each MISSION_REQUEST_INT with
+    /// MISSION_ITEM_INT, or a legacy MISSION_REQUEST with MISSION_ITEM, until
+    /// the vehicle ends the transaction with MISSION_ACK. Repeated requests
+    /// are answered again; MISSION_COUNT or the last item is resent when the
+    /// vehicle goes quiet.
+    pub fn upload_mission(&self, items: &[MissionItem]) -> Result<()> {
+        self.upload_items(MavMissionType::MAV_MISSION_TYPE_MISSION, items)
+    }
+
+    /// Replaces the items from sequence number `start` onwards with `items`,
+    /// leaving the rest of the mission in place.
+    ///
+    /// Uses MISSION_WRITE_PARTIAL_LIST and otherwise works like
+    /// [`Vehicle::upload_mission`]. The range must lie within the current
+    /// mission.
+    pub fn write_mission_items(&self, start: u16, items: &[MissionItem]) -> Result<()> {
+        if items.is_empty() {
+            return Ok(());
+        }
+        let messages = self.subscribe();
+        let partial_list =
+            MavMessage::MISSION_WRITE_PARTIAL_LIST(MISSION_WRITE_PARTIAL_LIST_DATA {
+                target_system: self.system_id(),
+                target_component: self.component_id(),
+                start_index: start as i16,
+                end_index: (start as usize + items.len() - 1) as i16,
+                ..Default::default()
+            });
+        let mission_type = MavMissionType::MAV_MISSION_TYPE_MISSION;
+        self.send_mission_items(&messages, partial_list, mission_type, start, items)
+    }
+
+    /// Downloads the vehicle's mission, ordered by sequence number.
+    ///
+    /// Items are requested one at a time with MISSION_REQUEST_INT; one that
+    /// does not arrive is requested again, and items that arrive out of
+    /// order are kept. The download ends with a MISSION_ACK so the vehicle
+    /// leaves its transaction state.
+    pub fn download_mission(&self) -> Result<Vec<MissionItem>> {
+        self.download_items(MavMissionType::MAV_MISSION_TYPE_MISSION)
+    }
+
+    /// Removes every mission item from the vehicle.
+    pub fn clear_mission(&self) -> Result<()> {
+        self.clear_items(MavMissionType::MAV_MISSION_TYPE_MISSION)
+    }
+
+    /// Makes item `seq` the one the vehicle flies to next.
+    ///
+    /// Sends MAV_CMD_DO_SET_MISSION_CURRENT; autopilots that answer
+    /// MAV_RESULT_UNSUPPORTED get MISSION_SET_CURRENT instead, confirmed by
+    /// the next MISSION_CURRENT.
+    pub fn set_current_item(&self, seq: u16) -> Result<()> {
+        match self.send_command(
+            MavCmd::MAV_CMD_DO_SET_MISSION_CURRENT,
+            [seq as f32, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0],
+        ) {
+            Err(Error::CommandFailed {
+                result: MavResult::MAV_RESULT_UNSUPPORTED,
+                ..
+            }) => {}
+            result => return result,
+        }
+        let messages = self.subscribe();
+        for _ in 0..=MISSION_RETRIES {
+            self.send(&MavMessage::MISSION_SET_CURRENT(MISSION_SET_CURRENT_DATA {
+                target_system: self.system_id(),
+                target_component: self.component_id(),
+                seq,
+            }))?;
+            match messages.wait_for(
+                MISSION_TIMEOUT,
+                "MISSION_CURRENT",
+                |header, msg| match msg {
+                    MavMessage::MISSION_CURRENT(data)
+                        if header.component_id == self.component_id() && data.seq == seq =>
+                    {
+                        Some(())
+                    }
+                    _ => None,
+                },
+            ) {
+                Err(Error::Timeout(_)) => continue,
+                result => return result,
+            }
+        }
+        Err(Error::Timeout("MISSION_CURRENT"))
+    }
+
+    /// Uploads `items` as the vehicle's list of `mission_type`: its mission,
+    /// geofence or rally points.
+    pub(crate) fn upload_items(
+        &self,
+        mission_type: MavMissionType,
+        items: &[MissionItem],
+    ) -> Result<()> {
+        let messages = self.subscribe();
+        let count = MavMessage::MISSION_COUNT(MISSION_COUNT_DATA {
+            target_system: self.system_id(),
+            target_component: self.component_id(),
+            count: items.len() as u16,
+            mission_type,
+            ..Default::default()
+        });
+        self.send_mission_items(&messages, count, mission_type, 0, items)
+    }
+
+    /// Downloads the vehicle's list of `mission_type`.
+    pub(crate) fn download_items(&self, mission_type: MavMissionType) -> Result<Vec<MissionItem>> {
+        let messages = self.subscribe();
+        let count = self.request_mission_count(&messages, mission_type)?;
+        let mut items: Vec<Option<MissionItem>> = vec![None; count as usize];
+        let mut requested = None;
+        let mut retries = 0;
+        while let Some(seq) = items.iter().position(Option::is_none) {
+            if requested != Some(seq) {
+                self.send(&MavMessage::MISSION_REQUEST_INT(MISSION_REQUEST_INT_DATA {
+                    target_system: self.system_id(),
+                    target_component: self.component_id(),
+                    seq: seq as u16,
+                    mission_type,
+                }))?;
+                requested = Some(seq);
+            }
+            let received =
+                messages.wait_for(
+                    MISSION_TIMEOUT,
+                    "MISSION_ITEM_INT",
+                    |header, msg| match msg {
+                        MavMessage::MISSION_ITEM_INT(data)
+                            if header.component_id == self.component_id()
+                                && data.mission_type == mission_type
+                                && items.get(data.seq as usize) == Some(&None) =>
+                        {
+                            Some(Ok(data.clone()))
+                        }
+                        MavMessage::MISSION_ACK(data)
+                            if header.component_id == self.component_id()
+                                && data.mission_type == mission_type =>
+                        {
+                            Some(Err(data.mavtype))
+                        }
+                        _ => None,
+                    },
+                );
+            match received {
+                Ok(Ok(data)) => {
+                    items[data.seq as usize] = Some(MissionItem::from(&data));
+                    retries = 0;
+                }
+                Ok(Err(result)) => return Err(Error::MissionFailed(result)),
+                Err(Error::Timeout(_)) if retries < MISSION_RETRIES => {
+                    retries += 1;
+                    requested = None;
+                }
+                Err(e @ Error::Timeout(_)) => {
+                    self.send_mission_ack(
+                        mission_type,
+                        MavMissionResult::MAV_MISSION_OPERATION_CANCELLED,
+                    )?;
+                    return Err(e);
+                }
+                Err(e) => return Err(e),
+            }
+        }
+        if count > 0 {
+            self.send_mission_ack(mission_type, MavMissionResult::MAV_MISSION_ACCEPTED)?;
+        }
+        Ok(items.into_iter().flatten().collect())
+    }
+
+    /// Removes the vehicle's whole list of `mission_type`.
+    pub(crate) fn clear_items(&self, mission_type: MavMissionType) -> Result<()> {
+        let messages = self.subscribe();
+        for _ in 0..=MISSION_RETRIES {
+            self.send(&MavMessage::MISSION_CLEAR_ALL(MISSION_CLEAR_ALL_DATA {
+                target_system: self.system_id(),
+                target_component: self.component_id(),
+                mission_type,
+            }))?;
+            match messages.wait_for(MISSION_TIMEOUT, "MISSION_ACK", |header, msg| match msg {
+                MavMessage::MISSION_ACK(data)
+                    if header.component_id == self.component_id()
+                        && data.mission_type == mission_type =>
+                {
+                    Some(data.mavtype)
+                }
+                _ => None,
+            }) {
+                Err(Error::Timeout(_)) => continue,
+                result => return mission_result(result?),
+            }
+        }
+        Err(Error::Timeout("MISSION_ACK"))
+    }
+
+    /// Sends `request`, which starts an upload at sequence number `start`,
+    /// then answers the vehicle's item requests until it acknowledges.
+    fn send_mission_items(
+        &self,
+        messages: &Subscription,
+        request: MavMessage,
+        mission_type: MavMissionType,
+        start: u16,
+        items: &[MissionItem],
+    ) -> Result<()> {
+        let mut last_sent = request;
+        self.send(&last_sent)?;
+        let mut retries = 0;
+        let mut waiting_for = "MISSION_REQUEST_INT";
+        loop {
+            let received = messages.wait_for(MISSION_TIMEOUT, waiting_for, |header, msg| {
+                let requested_type = match msg {
+                    MavMessage::MISSION_REQUEST_INT(data) => data.mission_type,
+                    MavMessage::MISSION_REQUEST(data) => data.mission_type,
+                    MavMessage::MISSION_ACK(data) => data.mission_type,
+                    _ => return None,
+                };
+                (header.component_id == self.component_id() && requested_type == mission_type)
+                    .then(|| msg.clone())
+            });
+            let message = match received {
+                Err(Error::Timeout(_)) if retries < MISSION_RETRIES => {
+                    retries += 1;
+                    self.send(&last_sent)?;
+                    continue;
+                }
+                received => received?,
+            };
+            let (seq, legacy) = match message {
+                MavMessage::MISSION_REQUEST_INT(data) => (data.seq, false),
+                MavMessage::MISSION_REQUEST(data) => (data.seq, true),
+                MavMessage::MISSION_ACK(data) => return mission_result(data.mavtype),
+                _ => continue,
+            };
+            let Some(item) = seq
+                .checked_sub(start)
+                .and_then(|index| items.get(index as usize))
+            else {
+                self.send_mission_ack(
+                    mission_type,
+                    MavMissionResult::MAV_MISSION_INVALID_SEQUENCE,
+                )?;
+                return Err(Error::MissionFailed(
+                    MavMissionResult::MAV_MISSION_INVALID_SEQUENCE,
+                ));
+            };
+            let item = MISSION_ITEM_INT_DATA {
+                target_system: self.system_id(),
+                target_component: self.component_id(),
+                mission_type,
+                ..item.to_mission_item_int(seq)
+            };
+            last_sent = if legacy {
+                MavMessage::MISSION_ITEM(float_item(&item))
+            } else {
+                MavMessage::MISSION_ITEM_INT(item)
+            };
+            self.send(&last_sent)?;
+            retries = 0;
+            if (seq - start) as usize + 1 == items.len() {
+                waiting_for = "MISSION_ACK";
+            }
+        }
+    }
+
+    /// Sends MISSION_REQUEST_LIST until the vehicle answers with MISSION_COUNT.
+    fn request_mission_count(
+        &self,
+        messages: &Subscription,
+        mission_type: MavMissionType,
+    ) -> Result<u16> {
+        for _ in 0..=MISSION_RETRIES {
+            self.send(&MavMessage::MISSION_REQUEST_LIST(
+                MISSION_REQUEST_LIST_DATA {
+                    target_system: self.system_id(),
+                    target_component: self.component_id(),
+                    mission_type,
+                },
+            ))?;
+            match messages.wait_for(MISSION_TIMEOUT, "MISSION_COUNT", |header, msg| match msg {
+                MavMessage::MISSION_COUNT(data)
+                    if header.component_id == self.component_id()
+                        && data.mission_type == mission_type =>
+                {
+                    Some(data.count)
+                }
+                _ => None,
+            }) {
+                Err(Error::Timeout(_)) => continue,
+                result => return result,
+            }
+        }
+        Err(Error::Timeout("MISSION_COUNT"))
+    }
+
+    /// Ends a transaction the vehicle started, with `result` as the outcome.
+    fn send_mission_ack(
+        &self,
+        mission_type: MavMissionType,
+        result: MavMissionResult,
+    ) -> Result<()> {
+        self.send(&MavMessage::MISSION_ACK(MISSION_ACK_DATA {
+            target_system: self.system_id(),
+            target_component: self.component_id(),
+            mavtype: result,
+            mission_type,
+            ..Default::default()
+        }))
+    }
+}
+
+fn mission_result(result: MavMissionResult) -> Result<()> {
+    match result {
+        MavMissionResult::MAV_MISSION_ACCEPTED => Ok(()),
+        result => Err(Error::MissionFailed(result)),
+    }
+}
+
+/// The MISSION_ITEM form of `item`, for vehicles that still send MISSION_REQUEST.
+fn float_item(item: &MISSION_ITEM_INT_DATA) -> MISSION_ITEM_DATA {
+    let scale = xy_scale(item.frame);
+    MISSION_ITEM_DATA {
+        param1: item.param1,
+        param2: item.param2,
+        param3: item.param3,
+        param4: item.param4,
+        x: (item.x as f64 / scale) as f32,
+        y: (item.y as f64 / scale) as f32,
+        z: item.z,
+        seq: item.seq,
+        command: item.command,
+        target_system: item.target_system,
+        target_component: item.target_component,
+        frame: item.frame,
+        current: item.current,
+        autocontinue: item.autocontinue,
+        mission_type: item.mission_type,
+    }
+}
+
+/// What MISSION_ITEM_INT x and y are multiplied by in `frame`: degE7 in
+/// global frames, metres * 1e4 in local ones and plain param5/param6 in
+/// MAV_FRAME_MISSION.
+pub(crate) fn xy_scale(frame: MavFrame) -> f64 {
+    match frame {
+        frame if is_global(frame) => 1e7,
+        MavFrame::MAV_FRAME_MISSION => 1.0,
+        _ => 1e4,
+    }
+}
+
+/// `value`, in degrees or metres, as a MISSION_ITEM_INT x or y in `frame`;
+/// `None` if it is not finite or does not fit.
+pub(crate) fn to_xy(value: f64, frame: MavFrame) -> Option<i32> {
+    let scaled = (value * xy_scale(frame)).round();
+    (i32::MIN as f64..=i32::MAX as f64)
+        .contains(&scaled)
+        .then_some(scaled as i32)
+}
+
+/// Whether x and y are latitude and longitude in `frame`.
+fn is_global(frame: MavFrame) -> bool {
+    matches!(
+        frame,
+        MavFrame::MAV_FRAME_GLOBAL
+            | MavFrame::MAV_FRAME_GLOBAL_INT
+            | MavFrame::MAV_FRAME_GLOBAL_RELATIVE_ALT
+            | MavFrame::MAV_FRAME_GLOBAL_RELATIVE_ALT_INT
+            | MavFrame::MAV_FRAME_GLOBAL_TERRAIN_ALT
+            | MavFrame::MAV_FRAME_GLOBAL_TERRAIN_ALT_INT
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    use mavlink::{
+        Message,
+        ardupilotmega::{MISSION_CURRENT_DATA, MISSION_REQUEST_DATA},
+    };
+
+    use super::*;
+    use crate::mock::{self, MockPeer};
+
+    fn mission() -> Vec<MissionItem> {
+        vec![
+            MissionItem::takeoff(20.0),
+            MissionItem::waypoint(-35.3612577, 149.165172, 50.0).unwrap(),
+            MissionItem::rtl(),
+        ]
+    }
+
+    fn request_int(seq: u16) -> MavMessage {
+        MavMessage::MISSION_REQUEST_INT(MISSION_REQUEST_INT_DATA {
+            seq,
+            ..Default::default()
+        })
+    }
+
+    fn mission_ack(result: MavMissionResult) -> MavMessage {
+        MavMessage::MISSION_ACK(MISSION_ACK_DATA {
+            mavtype: result,
+            ..Default::default()
+        })
+    }
+
+    fn names(peer: &MockPeer) -> Vec<&'static str> {
+        peer.sent().iter().map(Message::message_name).collect()
+    }
+
+    #[test]
+    fn upload_answers_repeated_and_legacy_requests() {
+        let repeated = AtomicBool::new(false);
+        let (vehicle, peer) = mock::vehicle(move |msg| match msg {
+            MavMessage::MISSION_COUNT(_) => vec![request_int(0)],
+            MavMessage::MISSION_ITEM_INT(data) if data.seq == 0 => {
+                if repeated.swap(true, Ordering::Relaxed) {
+                    vec![request_int(1)]
+                } else {
+                    vec![request_int(0)]
+                }
+            }
+            MavMessage::MISSION_ITEM_INT(_) => {
+                vec![MavMessage::MISSION_REQUEST(MISSION_REQUEST_DATA {
+                    seq: 2,
+                    ..Default::default()
+                })]
+            }
+            MavMessage::MISSION_ITEM(_) => {
+                vec![mission_ack(MavMissionResult::MAV_MISSION_ACCEPTED)]
+            }
+            _ => Vec::new(),
+        });
+        vehicle.upload_mission(&mission()).unwrap();
+        assert_eq!(
+            names(&peer),
+            [
+                "MISSION_COUNT",
+                "MISSION_ITEM_INT",
+                "MISSION_ITEM_INT",
+                "MISSION_ITEM_INT",
+                "MISSION_ITEM"
+            ]
+        );
+        let sent = peer.sent();
+        let MavMessage::MISSION_ITEM_INT(waypoint) = &sent[3] else {
+            panic!("sent {sent:?}");
+        };
+        assert_eq!(
+            (waypoint.seq, waypoint.x, waypoint.y),
+            (1, -353612577, 1491651720)
+        );
+        assert_eq!(waypoint.target_system, 1);
+        let MavMessage::MISSION_ITEM(rtl) = &sent[4] else {
+            panic!("sent {sent:?}");
+        };
+        assert_eq!(rtl.seq, 2);
+        assert_eq!(rtl.command, MavCmd::MAV_CMD_NAV_RETURN_TO_LAUNCH);
+    }
+
+    #[test]
+    fn legacy_items_carry_degrees() {
+        let item = MissionItem::waypoint(-35.3612577, 149.165172, 50.0)
+            .unwrap()
+            .to_mission_item_int(1);
+        let legacy = float_item(&item);
+        assert_eq!(
+            (legacy.x, legacy.y),
+            (-35.3612577_f64 as f32, 149.165172_f64 as f32)
+        );
+        let local = MissionItem {
+            frame: MavFrame::MAV_FRAME_LOCAL_NED,
+            x: 12_345,
+            ..MissionItem::new(MavCmd::MAV_CMD_NAV_WAYPOINT, MavFrame::MAV_FRAME_LOCAL_NED)
+        };
+        assert_eq!(float_item(&local.to_mission_item_int(0)).x, 1.2345);
+    }
+
+    #[test]
+    fn upload_returns_a_nack_as_mission_failed() {
+        let (vehicle, _peer) = mock::vehicle(|msg| match msg {
+            MavMessage::MISSION_COUNT(_) => {
+                vec![mission_ack(MavMissionResult::MAV_MISSION_NO_SPACE)]
+            }
+            _ => Vec::new(),
+        });
+        assert!(matches!(
+            vehicle.upload_mission(&mission()),
+            Err(Error::MissionFailed(MavMissionResult::MAV_MISSION_NO_SPACE))
+        ));
+    }
+
+    #[test]
+    fn upload_refuses_requests_past_the_end() {
+        let (vehicle, peer) = mock::vehicle(|msg| match msg {
+            MavMessage::MISSION_COUNT(_) => vec![request_int(3)],
+            _ => Vec::new(),
+        });
+        assert!(matches!(
+            vehicle.upload_mission(&mission()),
+            Err(Error::MissionFailed(
+                MavMissionResult::MAV_MISSION_INVALID_SEQUENCE
+            ))
+        ));
+        assert_eq!(names(&peer), ["MISSION_COUNT", "MISSION_ACK"]);
+    }
+
+    #[test]
+    fn upload_resends_when_the_vehicle_goes_quiet() {
+        let counted = AtomicBool::new(false);
+        let (vehicle, peer) = mock::vehicle(move |msg| match msg {
+            // The first MISSION_COUNT is lost.
+            MavMessage::MISSION_COUNT(_) if !counted.swap(true, Ordering::Relaxed) => Vec::new(),
+            MavMessage::MISSION_COUNT(_) => vec![request_int(0)],
+            MavMessage::MISSION_ITEM_INT(_) => {
+                vec![mission_ack(MavMissionResult::MAV_MISSION_ACCEPTED)]
+            }
+            _ => Vec::new(),
+        });
+        vehicle.upload_mission(&mission()[..1]).unwrap();
+        assert_eq!(
+            names(&peer),
+            ["MISSION_COUNT", "MISSION_COUNT", "MISSION_ITEM_INT"]
+        );
+    }
+
+    fn item_int(seq: u16) -> MavMessage {
+        MavMessage::MISSION_ITEM_INT(MISSION_ITEM_INT_DATA {
+            z: seq as f32,
+            ..mission()[seq as usize].to_mission_item_int(seq)
+        })
+    }
+
+    fn count(count: u16) -> MavMessage {
+        MavMessage::MISSION_COUNT(MISSION_COUNT_DATA {
+            count,
+            ..Default::default()
+        })
+    }
+
+    fn requested_seqs(peer: &MockPeer) -> Vec<u16> {
+        peer.sent()
+            .iter()
+            .filter_map(|msg| match msg {
+                MavMessage::MISSION_REQUEST_INT(data) => Some(data.seq),
+                _ => None,
+            })
+            .collect()
+    }
+
+    fn last_ack(peer: &MockPeer) -> Option<MavMissionResult> {
+        match peer.sent().last()? {
+            MavMessage::MISSION_ACK(data) => Some(data.mavtype),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn download_keeps_items_that_arrive_out_of_order() {
+        let (vehicle, peer) = mock::vehicle(|msg| match msg {
+            MavMessage::MISSION_REQUEST_LIST(_) => vec![count(3)],
+            // Item 2 arrives early, unrequested.
+            MavMessage::MISSION_REQUEST_INT(data) if data.seq == 0 => {
+                vec![item_int(2), item_int(0)]
+            }
+            MavMessage::MISSION_REQUEST_INT(data) => vec![item_int(data.seq)],
+            _ => Vec::new(),
+        });
+        let items = vehicle.download_mission().unwrap();
+        let altitudes: Vec<_> = items.iter().map(|item| item.z).collect();
+        assert_eq!(altitudes, [0.0, 1.0, 2.0]);
+        assert_eq!(items[2].command, MavCmd::MAV_CMD_NAV_RETURN_TO_LAUNCH);
+        assert_eq!(requested_seqs(&peer), [0, 1]);
+        assert_eq!(
+            last_ack(&peer),
+            Some(MavMissionResult::MAV_MISSION_ACCEPTED)
+        );
+    }
+
+    #[test]
+    fn download_of_an_empty_mission_sends_no_ack() {
+        let (vehicle, peer) = mock::vehicle(|msg| match msg {
+            MavMessage::MISSION_REQUEST_LIST(_) => vec![count(0)],
+            _ => Vec::new(),
+        });
+        assert!(vehicle.download_mission().unwrap().is_empty());
+        assert_eq!(names(&peer), ["MISSION_REQUEST_LIST"]);
+    }
+
+    #[test]
+    fn download_re_requests_then_cancels() {
+        let (vehicle, peer) = mock::vehicle(|msg| match msg {
+            MavMessage::MISSION_REQUEST_LIST(_) => vec![count(2)],
+            MavMessage::MISSION_REQUEST_INT(data) if data.seq == 0 => vec![item_int(0)],
+            _ => Vec::new(),
+        });
+        assert!(matches!(
+            vehicle.download_mission(),
+            Err(Error::Timeout("MISSION_ITEM_INT"))
+        ));
+        assert_eq!(requested_seqs(&peer), [0, 1, 1, 1, 1]);
+        assert_eq!(
+            last_ack(&peer),
+            Some(MavMissionResult::MAV_MISSION_OPERATION_CANCELLED)
+        );
+    }
+
+    #[test]
+    fn partial_write_numbers_items_from_start() {
+        let (vehicle, peer) = mock::vehicle(|msg| match msg {
+            MavMessage::MISSION_WRITE_PARTIAL_LIST(data) => {
+                vec![request_int(data.start_index as u16)]
+            }
+            MavMessage::MISSION_ITEM_INT(data) if data.seq == 5 => vec![request_int(6)],
+            MavMessage::MISSION_ITEM_INT(_) => {
+                vec![mission_ack(MavMissionResult::MAV_MISSION_ACCEPTED)]
+            }
+            _ => Vec::new(),
+        });
+        let items = &mission()[1..];
+        vehicle.write_mission_items(5, items).unwrap();
+        let sent = peer.sent();
+        let MavMessage::MISSION_WRITE_PARTIAL_LIST(range) = &sent[0] else {
+            panic!("sent {sent:?}");
+        };
+        assert_eq!((range.start_index, range.end_index), (5, 6));
+        let written: Vec<_> = sent[1..]
+            .iter()
+            .map(|msg| match msg {
+                MavMessage::MISSION_ITEM_INT(data) => (data.seq, MissionItem::from(data)),
+                other => panic!("sent {other:?}"),
+            })
+            .collect();
+        assert_eq!(written, [(5, items[0].clone()), (6, items[1].clone())]);
+    }
+
+    #[test]
+    fn clear_mission_waits_for_the_ack() {
+        let (vehicle, peer) = mock::vehicle(|msg| match msg {
+            MavMessage::MISSION_CLEAR_ALL(_) => {
+                vec![mission_ack(MavMissionResult::MAV_MISSION_ACCEPTED)]
+            }
+            _ => Vec::new(),
+        });
+        vehicle.clear_mission().unwrap();
+        assert_eq!(names(&peer), ["MISSION_CLEAR_ALL"]);
+    }
+
+    #[test]
+    fn set_current_item_falls_back_to_mission_set_current() {
+        let (vehicle, peer) = mock::vehicle(|msg| match msg {
+            MavMessage::COMMAND_LONG(data) => {
+                vec![mock::command_ack(
+                    data.command,
+                    MavResult::MAV_RESULT_UNSUPPORTED,
+                )]
+            }
+            MavMessage::MISSION_SET_CURRENT(data) => {
+                vec![MavMessage::MISSION_CURRENT(MISSION_CURRENT_DATA {
+                    seq: data.seq,
+                    ..Default::default()
+                })]
+            }
+            _ => Vec::new(),
+        });
+        vehicle.set_current_item(3).unwrap();
+        assert_eq!(names(&peer), ["COMMAND_LONG", "MISSION_SET_CURRENT"]);
+    }
+
+    #[test]
+    fn builders_fill_their_params() {
+        let speed = MissionItem::do_change_speed(12.5);
+        assert_eq!(speed.params, [1.0, 12.5, -1.0, 0.0]);
+        let waypoint = MissionItem::waypoint(-35.36, 149.16, 50.0)
+            .unwrap()
+            .hold(Duration::from_secs(5))
+            .accept_radius(2.0)
+            .param(ParamIndex::Param4, 90.0);
+        assert_eq!(waypoint.params, [5.0, 2.0, 0.0, 90.0]);
+        assert_eq!(MissionItem::do_jump(2, 3).params[..2], [2.0, 3.0]);
+    }
+
+    #[test]
+    fn builders_reject_invalid_positions() {
+        assert!(matches!(
+            MissionItem::waypoint(-353.6, 149.16, 50.0),
+            Err(Error::InvalidPosition(_))
+        ));
+        assert!(matches!(
+            MissionItem::loiter_time(-35.36, f64::NAN, 50.0, Duration::from_secs(5)),
+            Err(Error::InvalidPosition(_))
+        ));
+    }
+}
@@ -0,0 +1,251 @@
+use std::{fmt::Write as _, fs, path::Path};
+
+use mavlink::ardupilotmega::{MavCmd, MavFrame};
+use num_traits::FromPrimitive;
+
+use crate::{
+    error::{Error, Result},
+    mission::{MissionItem, to_xy, xy_scale},
+};
+
+/// First line of every QGroundControl WPL 110 file.
+const WPL_HEADER: &str = "QGC WPL 110";
+
+/// A mission in QGroundControl's WPL 110 text format (`.waypoints`), as
+/// written by QGroundControl and Mission Planner.
+///
+/// Each line after the header is one item, tab-separated:
+/// `INDEX CURRENT FRAME COMMAND P1 P2 P3 P4 X/LAT Y/LON Z AUTOCONTINUE`.
+/// Item 0 is home on ArduPilot, so the items go to the vehicle as they are.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct WaypointFile {
+    pub items: Vec<MissionItem>,
+}
+
+impl WaypointFile {
+    pub fn new(items: Vec<MissionItem>) -> Self {
+        Self { items }
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        Self::parse(&fs::read_to_string(path).map_err(Error::Io)?)
+    }
+
+    pub fn parse(text: &str) -> Result<Self> {
+        let mut file = Self::default();
+        let mut header = false;
+        for (number, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() {
+                continue;
+            }
+            let invalid = |message: String| Error::InvalidMissionFile {
+                line: number + 1,
+                message,
+            };
+            if !header {
+                if line != WPL_HEADER {
+                    return Err(invalid(format!("expected the header {WPL_HEADER}")));
+                }
+                header = true;
+                continue;
+            }
+            let fields: Vec<&str> = line.split('\t').map(str::trim).collect();
+            let [
+                index,
+                current,
+                frame,
+                command,
+                param1,
+                param2,
+                param3,
+                param4,
+                x,
+                y,
+                z,
+                autocontinue,
+            ] = fields.as_slice()
+            else {
+                return Err(invalid(
+                    "expected 12 tab-separated fields: INDEX CURRENT FRAME COMMAND \
+                     P1 P2 P3 P4 X Y Z AUTOCONTINUE"
+                        .to_string(),
+                ));
+            };
+            if index.parse() != Ok(file.items.len()) {
+                return Err(invalid(format!(
+                    "index {index:?} out of order, expected {}",
+                    file.items.len()
+                )));
+            }
+            let number_field = |name: &str, value: &str| {
+                value
+                    .parse::<f64>()
+                    .map_err(|_| invalid(format!("{name} {value:?} is not a number")))
+            };
+            let flag = |name: &str, value: &str| match value {
+                "0" => Ok(false),
+                "1" => Ok(true),
+                _ => Err(invalid(format!("{name} must be 0 or 1, not {value:?}"))),
+            };
+            let code = |name: &str, value: &str| {
+                value
+                    .parse::<u32>()
+                    .map_err(|_| invalid(format!("{name} {value:?} is not a number")))
+            };
+            let frame = MavFrame::from_u32(code("frame", frame)?)
+                .ok_or_else(|| invalid(format!("unknown frame {frame}")))?;
+            let command = MavCmd::from_u32(code("command", command)?)
+                .ok_or_else(|| invalid(format!("unknown command {command}")))?;
+            let scaled = |name: &str, value: &str| {
+                to_xy(number_field(name, value)?, frame)
+                    .ok_or_else(|| invalid(format!("{name} {value:?} is out of range")))
+            };
+            file.items.push(MissionItem {
+                command,
+                frame,
+                params: [
+                    number_field("param1", param1)? as f32,
+                    number_field("param2", param2)? as f32,
+                    number_field("param3", param3)? as f32,
+                    number_field("param4", param4)? as f32,
+                ],
+                x: scaled("x", x)?,
+                y: scaled("y", y)?,
+                z: number_field("z", z)? as f32,
+                current: flag("current", current)?,
+                autocontinue: flag("autocontinue", autocontinue)?,
+            });
+        }
+        if !header {
+            return Err(Error::InvalidMissionFile {
+                line: 1,
+                message: format!("expected the header {WPL_HEADER}"),
+            });
+        }
+        Ok(file)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        fs::write(path, self.to_text()).map_err(Error::Io)
+    }
+
+    /// The file contents; positions keep every digit of the degE7 values, so
+    /// a saved mission loads back unchanged.
+    pub fn to_text(&self) -> String {
+        let mut text = String::new();
+        let _ = writeln!(text, "{WPL_HEADER}");
+        for (seq, item) in self.items.iter().enumerate() {
+            let [param1, param2, param3, param4] = item.params;
+            let scale = xy_scale(item.frame);
+            let _ = writeln!(
+                text,
+                "{seq}\t{}\t{}\t{}\t{param1}\t{param2}\t{param3}\t{param4}\t{}\t{}\t{}\t{}",
+                item.current as u8,
+                item.frame as u32,
+                item.command as u32,
+                item.x as f64 / scale,
+                item.y as f64 / scale,
+                item.z,
+                item.autocontinue as u8
+            );
+        }
+        text
+    }
+}
+
+impl From<Vec<MissionItem>> for WaypointFile {
+    fn from(items: Vec<MissionItem>) -> Self {
+        Self::new(items)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    const QGC: &str = "QGC WPL 110
+0\t1\t0\t16\t0\t0\t0\t0\t-35.363262\t149.165237\t584.090000\t1
+1\t0\t3\t22\t0.00000000\t0.00000000\t0.00000000\t0.00000000\t0.00000000\t0.00000000\t20.000000\t1
+2\t0\t3\t16\t5\t2\t0\tnan\t-35.36125769\t149.16517199\t100\t1
+3\t0\t1\t16\t0\t0\t0\t0\t12.5\t-3.25\t-10\t0
+";
+
+    fn line_of(text: &str) -> usize {
+        match WaypointFile::parse(text) {
+            Err(Error::InvalidMissionFile { line, .. }) => line,
+            other => panic!("{text:?} parsed as {other:?}"),
+        }
+    }
+
+    #[test]
+    fn parses_qgc_files() {
+        let file = WaypointFile::parse(QGC).unwrap();
+        assert_eq!(file.items.len(), 4);
+        assert!(file.items[0].current);
+        assert_eq!((file.items[0].x, file.items[0].y), (-353632620, 1491652370));
+        assert_eq!(file.items[1].command, MavCmd::MAV_CMD_NAV_TAKEOFF);
+        assert_eq!(file.items[2].x, -353612577);
+        assert!(file.items[2].params[3].is_nan());
+        assert_eq!((file.items[3].x, file.items[3].y), (125000, -32500));
+        assert!(!file.items[3].autocontinue);
+    }
+
+    #[test]
+    fn round_trips_through_text() {
+        let items = vec![
+            MissionItem::waypoint(0.0, 0.0, 0.0)
+                .unwrap()
+                .frame(MavFrame::MAV_FRAME_GLOBAL),
+            MissionItem::takeoff(50.0),
+            MissionItem::waypoint(-35.36125769, 149.16517199, 100.0)
+                .unwrap()
+                .hold(Duration::from_secs(5))
+                .accept_radius(0.1),
+            MissionItem::do_jump(2, 3),
+            MissionItem::rtl(),
+        ];
+        let file = WaypointFile::new(items);
+        assert_eq!(WaypointFile::parse(&file.to_text()).unwrap(), file);
+    }
+
+    #[test]
+    fn reports_the_failing_line() {
+        assert_eq!(line_of(""), 1);
+        assert_eq!(line_of("QGC WPL 100\n"), 1);
+        assert_eq!(line_of("\nQGC WPL 110\n0\t0\t3\t16\t0\t0\t0\t0\t1\t2\n"), 3);
+        assert_eq!(line_of("QGC WPL 110\n0 0 3 16 0 0 0 0 1 2 3 1\n"), 2);
+        assert_eq!(
+            line_of("QGC WPL 110\n1\t0\t3\t16\t0\t0\t0\t0\t1\t2\t3\t1\n"),
+            2
+        );
+        assert_eq!(
+            line_of("QGC WPL 110\n0\t0\t99\t16\t0\t0\t0\t0\t1\t2\t3\t1\n"),
+            2
+        );
+        assert_eq!(
+            line_of("QGC WPL 110\n0\t0\t3\t9999\t0\t0\t0\t0\t1\t2\t3\t1\n"),
+            2
+        );
+        assert_eq!(
+            line_of("QGC WPL 110\n0\t2\t3\t16\t0\t0\t0\t0\t1\t2\t3\t1\n"),
+            2
+        );
+        assert_eq!(
+            line_of("QGC WPL 110\n0\t0\t3\t16\t0\t0\t0\t0\t1000\t2\t3\t1\n"),
+            2
+        );
+    }
+
+    #[test]
+    fn quotes_bad_values() {
+        let error =
+            WaypointFile::parse("QGC WPL 110\n0\t0\t3\t16\t0\t0\t0\t0\tx\t2\t3\t1\n").unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "invalid mission file, line 2: x \"x\" is not a number"
+        );
+    }
+}
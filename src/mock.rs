@@ -0,0 +1,165 @@
+//! A scripted MAVLink peer for unit tests.
+
+use std::{
+    io,
+    sync::{
+        Arc, Mutex,
+        mpsc::{self, Receiver, RecvTimeoutError, Sender},
+    },
+    thread,
+    time::Duration,
+};
+
+use mavlink::{
+    MavConnection, MavHeader, MavlinkVersion,
+    ardupilotmega::{COMMAND_ACK_DATA, HEARTBEAT_DATA, MavCmd, MavMessage, MavResult},
+    error::{MessageReadError, MessageWriteError},
+};
+
+use crate::{
+    station::{Connection, GroundStation},
+    vehicle::Vehicle,
+};
+
+/// Header of everything the mock vehicle sends.
+pub(crate) const VEHICLE: MavHeader = MavHeader {
+    system_id: 1,
+    component_id: 1,
+    sequence: 0,
+};
+
+/// How long `recv` blocks before reporting WouldBlock, like a socket timeout.
+const RECV_POLL: Duration = Duration::from_millis(5);
+
+const HEARTBEAT_INTERVAL: Duration = Duration::from_millis(20);
+
+type Script = Box<dyn FnMut(&MavMessage) -> Vec<MavMessage> + Send>;
+
+/// A message from the vehicle, or `None` to fail the link.
+type Incoming = Option<(MavHeader, MavMessage)>;
+
+/// A connection whose far end answers each sent message with the replies
+/// its script returns.
+struct MockLink {
+    incoming: Mutex<Receiver<Incoming>>,
+    peer: MockPeer,
+    script: Mutex<Script>,
+}
+
+/// The far end of a mock link, for sending unprompted messages and
+/// inspecting what the ground station sent.
+#[derive(Clone)]
+pub(crate) struct MockPeer {
+    incoming: Sender<Incoming>,
+    sent: Arc<Mutex<Vec<MavMessage>>>,
+}
+
+impl MockPeer {
+    /// Sends `message` as the vehicle.
+    pub(crate) fn send(&self, message: MavMessage) {
+        self.send_from(VEHICLE, message);
+    }
+
+    pub(crate) fn send_from(&self, header: MavHeader, message: MavMessage) {
+        let _ = self.incoming.send(Some((header, message)));
+    }
+
+    /// Sends a HEARTBEAT built by `heartbeat` every few milliseconds, from
+    /// another thread, until the link is dropped.
+    pub(crate) fn send_heartbeats(&self, heartbeat: impl Fn() -> HEARTBEAT_DATA + Send + 'static) {
+        let incoming = self.incoming.clone();
+        thread::spawn(move || {
+            while incoming
+                .send(Some((VEHICLE, MavMessage::HEARTBEAT(heartbeat()))))
+                .is_ok()
+            {
+                thread::sleep(HEARTBEAT_INTERVAL);
+            }
+        });
+    }
+
+    /// Fails the link as a dropped TCP connection would.
+    pub(crate) fn close(&self) {
+        let _ = self.incoming.send(None);
+    }
+
+    /// Everything the ground station has sent so far.
+    pub(crate) fn sent(&self) -> Vec<MavMessage> {
+        self.sent.lock().unwrap().clone()
+    }
+}
+
+impl MavConnection<MavMessage> for MockLink {
+    fn recv(&self) -> Result<(MavHeader, MavMessage), MessageReadError> {
+        match self.incoming.lock().unwrap().recv_timeout(RECV_POLL) {
+            Ok(Some(message)) => Ok(message),
+            Err(RecvTimeoutError::Timeout) => {
+                Err(io::Error::from(io::ErrorKind::WouldBlock).into())
+            }
+            Ok(None) | Err(RecvTimeoutError::Disconnected) => {
+                Err(io::Error::from(io::ErrorKind::ConnectionAborted).into())
+            }
+        }
+    }
+
+    fn try_recv(&self) -> Result<(MavHeader, MavMessage), MessageReadError> {
+        self.recv()
+    }
+
+    fn send(&self, _header: &MavHeader, data: &MavMessage) -> Result<usize, MessageWriteError> {
+        self.peer.sent.lock().unwrap().push(data.clone());
+        for reply in (self.script.lock().unwrap())(data) {
+            self.peer.send(reply);
+        }
+        Ok(0)
+    }
+
+    fn set_protocol_version(&mut self, _version: MavlinkVersion) {}
+
+    fn protocol_version(&self) -> MavlinkVersion {
+        MavlinkVersion::V2
+    }
+
+    fn set_allow_recv_any_version(&mut self, _allow: bool) {}
+
+    fn allow_recv_any_version(&self) -> bool {
+        true
+    }
+}
+
+/// A ground station linked to a peer that answers with `script`.
+pub(crate) fn station(
+    script: impl FnMut(&MavMessage) -> Vec<MavMessage> + Send + 'static,
+) -> (GroundStation, MockPeer) {
+    let (tx, rx) = mpsc::channel();
+    let peer = MockPeer {
+        incoming: tx,
+        sent: Arc::default(),
+    };
+    let link: Connection = Box::new(MockLink {
+        incoming: Mutex::new(rx),
+        peer: peer.clone(),
+        script: Mutex::new(Box::new(script)),
+    });
+    (GroundStation::new(link), peer)
+}
+
+/// The vehicle at [`VEHICLE`], answering with `script`.
+pub(crate) fn vehicle(
+    script: impl FnMut(&MavMessage) -> Vec<MavMessage> + Send + 'static,
+) -> (Vehicle, MockPeer) {
+    let (station, peer) = station(script);
+    (
+        Vehicle::new(station, VEHICLE.system_id, VEHICLE.component_id),
+        peer,
+    )
+}
+
+/// A COMMAND_ACK for `command`, without target fields as older autopilots send it.
+pub(crate) fn command_ack(command: MavCmd, result: MavResult) -> MavMessage {
+    MavMessage::COMMAND_ACK(COMMAND_ACK_DATA {
+        command,
+        result,
+        ..Default::default()
+    })
+}
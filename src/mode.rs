@@ -0,0 +1,554 @@
+use std::{
+    fmt,
+    str::FromStr,
+    time::{Duration, Instant},
+};
+
+use mavlink::ardupilotmega::{
+    HEARTBEAT_DATA, MavAutopilot, MavCmd, MavMessage, MavModeFlag, MavType,
+};
+
+use crate::{
+    error::{Error, Result},
+    vehicle::Vehicle,
+};
+
+/// How long to wait for a HEARTBEAT showing the new mode after the ACK.
+const MODE_CONFIRM_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// Defines a custom_mode table with GCS-style names for display and parsing.
+macro_rules! custom_modes {
+    ($(#[$meta:meta])* $name:ident, $label:literal { $($mode:ident = $value:expr,)* }) => {
+        $(#[$meta])*
+        #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+        #[allow(non_camel_case_types, clippy::upper_case_acronyms)]
+        #[repr(u32)]
+        pub enum $name {
+            $($mode = $value,)*
+        }
+
+        impl $name {
+            pub const ALL: &[Self] = &[$(Self::$mode,)*];
+
+            pub fn from_custom_mode(custom_mode: u32) -> Option<Self> {
+                Self::ALL
+                    .iter()
+                    .copied()
+                    .find(|mode| mode.custom_mode() == custom_mode)
+            }
+
+            pub fn custom_mode(self) -> u32 {
+                self as u32
+            }
+        }
+
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str(match self {
+                    $(Self::$mode => stringify!($mode),)*
+                })
+            }
+        }
+
+        impl FromStr for $name {
+            type Err = Error;
+
+            fn from_str(s: &str) -> Result<Self> {
+                Self::ALL
+                    .iter()
+                    .copied()
+                    .find(|mode| mode.to_string().eq_ignore_ascii_case(s))
+                    .ok_or_else(|| Error::InvalidMode(format!("{s} is not a {} mode", $label)))
+            }
+        }
+    };
+}
+
+custom_modes! {
+    /// ArduCopter, also used by ArduPilot helicopters.
+    CopterMode, "Copter" {
+        STABILIZE = 0,
+        ACRO = 1,
+        ALT_HOLD = 2,
+        AUTO = 3,
+        GUIDED = 4,
+        LOITER = 5,
+        RTL = 6,
+        CIRCLE = 7,
+        LAND = 9,
+        DRIFT = 11,
+        SPORT = 13,
+        FLIP = 14,
+        AUTOTUNE = 15,
+        POSHOLD = 16,
+        BRAKE = 17,
+        THROW = 18,
+        AVOID_ADSB = 19,
+        GUIDED_NOGPS = 20,
+        SMART_RTL = 21,
+        FLOWHOLD = 22,
+        FOLLOW = 23,
+        ZIGZAG = 24,
+        SYSTEMID = 25,
+        AUTOROTATE = 26,
+        AUTO_RTL = 27,
+        TURTLE = 28,
+    }
+}
+
+custom_modes! {
+    /// ArduPlane, including the QuadPlane Q-modes.
+    PlaneMode, "Plane" {
+        MANUAL = 0,
+        CIRCLE = 1,
+        STABILIZE = 2,
+        TRAINING = 3,
+        ACRO = 4,
+        FBWA = 5,
+        FBWB = 6,
+        CRUISE = 7,
+        AUTOTUNE = 8,
+        AUTO = 10,
+        RTL = 11,
+        LOITER = 12,
+        TAKEOFF = 13,
+        AVOID_ADSB = 14,
+        GUIDED = 15,
+        INITIALISING = 16,
+        QSTABILIZE = 17,
+        QHOVER = 18,
+        QLOITER = 19,
+        QLAND = 20,
+        QRTL = 21,
+        QAUTOTUNE = 22,
+        QACRO = 23,
+        THERMAL = 24,
+        LOITER_ALT_QLAND = 25,
+        AUTOLAND = 26,
+    }
+}
+
+custom_modes! {
+    /// ArduRover, for both rovers and boats.
+    RoverMode, "Rover" {
+        MANUAL = 0,
+        ACRO = 1,
+        STEERING = 3,
+        HOLD = 4,
+        LOITER = 5,
+        FOLLOW = 6,
+        SIMPLE = 7,
+        DOCK = 8,
+        CIRCLE = 9,
+        AUTO = 10,
+        RTL = 11,
+        SMART_RTL = 12,
+        GUIDED = 15,
+        INITIALISING = 16,
+    }
+}
+
+custom_modes! {
+    /// ArduSub.
+    SubMode, "Sub" {
+        STABILIZE = 0,
+        ACRO = 1,
+        ALT_HOLD = 2,
+        AUTO = 3,
+        GUIDED = 4,
+        CIRCLE = 7,
+        SURFACE = 9,
+        POSHOLD = 16,
+        MANUAL = 19,
+        MOTOR_DETECT = 20,
+        SURFTRAK = 21,
+    }
+}
+
+/// PX4 packs its main mode into bits 16-23 of custom_mode and the sub mode into bits 24-31.
+const fn px4_mode(main_mode: u8, sub_mode: u8) -> u32 {
+    ((main_mode as u32) << 16) | ((sub_mode as u32) << 24)
+}
+
+custom_modes! {
+    /// PX4, named `<main mode>_<sub mode>` where a sub mode exists.
+    Px4Mode, "PX4" {
+        MANUAL = px4_mode(1, 0),
+        ALTCTL = px4_mode(2, 0),
+        POSCTL = px4_mode(3, 0),
+        POSCTL_ORBIT = px4_mode(3, 1),
+        AUTO_READY = px4_mode(4, 1),
+        AUTO_TAKEOFF = px4_mode(4, 2),
+        AUTO_LOITER = px4_mode(4, 3),
+        AUTO_MISSION = px4_mode(4, 4),
+        AUTO_RTL = px4_mode(4, 5),
+        AUTO_LAND = px4_mode(4, 6),
+        AUTO_FOLLOW_TARGET = px4_mode(4, 8),
+        AUTO_PRECLAND = px4_mode(4, 9),
+        AUTO_VTOL_TAKEOFF = px4_mode(4, 10),
+        ACRO = px4_mode(5, 0),
+        OFFBOARD = px4_mode(6, 0),
+        STABILIZED = px4_mode(7, 0),
+    }
+}
+
+impl Px4Mode {
+    pub fn main_mode(self) -> u8 {
+        (self.custom_mode() >> 16) as u8
+    }
+
+    pub fn sub_mode(self) -> u8 {
+        (self.custom_mode() >> 24) as u8
+    }
+}
+
+/// Which custom_mode table a vehicle uses.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ModeFamily {
+    Copter,
+    Plane,
+    Rover,
+    Sub,
+    Px4,
+}
+
+impl ModeFamily {
+    pub fn from_heartbeat(heartbeat: &HEARTBEAT_DATA) -> Option<Self> {
+        match heartbeat.autopilot {
+            MavAutopilot::MAV_AUTOPILOT_PX4 => Some(ModeFamily::Px4),
+            MavAutopilot::MAV_AUTOPILOT_ARDUPILOTMEGA => match heartbeat.mavtype {
+                MavType::MAV_TYPE_QUADROTOR
+                | MavType::MAV_TYPE_HEXAROTOR
+                | MavType::MAV_TYPE_OCTOROTOR
+                | MavType::MAV_TYPE_TRICOPTER
+                | MavType::MAV_TYPE_COAXIAL
+                | MavType::MAV_TYPE_HELICOPTER
+                | MavType::MAV_TYPE_DODECAROTOR
+                | MavType::MAV_TYPE_DECAROTOR
+                | MavType::MAV_TYPE_GENERIC_MULTIROTOR => Some(ModeFamily::Copter),
+                MavType::MAV_TYPE_FIXED_WING
+                | MavType::MAV_TYPE_VTOL_TAILSITTER_DUOROTOR
+                | MavType::MAV_TYPE_VTOL_TAILSITTER_QUADROTOR
+                | MavType::MAV_TYPE_VTOL_TILTROTOR
+                | MavType::MAV_TYPE_VTOL_FIXEDROTOR
+                | MavType::MAV_TYPE_VTOL_TAILSITTER
+                | MavType::MAV_TYPE_VTOL_TILTWING => Some(ModeFamily::Plane),
+                MavType::MAV_TYPE_GROUND_ROVER | MavType::MAV_TYPE_SURFACE_BOAT => {
+                    Some(ModeFamily::Rover)
+                }
+                MavType::MAV_TYPE_SUBMARINE => Some(ModeFamily::Sub),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+}
+
+/// A flight mode of any supported vehicle family.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FlightMode {
+    Copter(CopterMode),
+    Plane(PlaneMode),
+    Rover(RoverMode),
+    Sub(SubMode),
+    Px4(Px4Mode),
+}
+
+impl FlightMode {
+    /// Decodes the mode a HEARTBEAT reports, using its mavtype and autopilot
+    /// to pick the table.
+    pub fn from_heartbeat(heartbeat: &HEARTBEAT_DATA) -> Option<Self> {
+        Self::from_custom_mode(
+            ModeFamily::from_heartbeat(heartbeat)?,
+            heartbeat.custom_mode,
+        )
+    }
+
+    pub fn from_custom_mode(family: ModeFamily, custom_mode: u32) -> Option<Self> {
+        match family {
+            ModeFamily::Copter => CopterMode::from_custom_mode(custom_mode).map(Self::Copter),
+            ModeFamily::Plane => PlaneMode::from_custom_mode(custom_mode).map(Self::Plane),
+            ModeFamily::Rover => RoverMode::from_custom_mode(custom_mode).map(Self::Rover),
+            ModeFamily::Sub => SubMode::from_custom_mode(custom_mode).map(Self::Sub),
+            ModeFamily::Px4 => Px4Mode::from_custom_mode(custom_mode).map(Self::Px4),
+        }
+    }
+
+    /// Looks `name` up in the table of `family`, ignoring case.
+    pub fn parse(family: ModeFamily, name: &str) -> Result<Self> {
+        Ok(match family {
+            ModeFamily::Copter => Self::Copter(name.parse()?),
+            ModeFamily::Plane => Self::Plane(name.parse()?),
+            ModeFamily::Rover => Self::Rover(name.parse()?),
+            ModeFamily::Sub => Self::Sub(name.parse()?),
+            ModeFamily::Px4 => Self::Px4(name.parse()?),
+        })
+    }
+
+    pub fn family(self) -> ModeFamily {
+        match self {
+            Self::Copter(_) => ModeFamily::Copter,
+            Self::Plane(_) => ModeFamily::Plane,
+            Self::Rover(_) => ModeFamily::Rover,
+            Self::Sub(_) => ModeFamily::Sub,
+            Self::Px4(_) => ModeFamily::Px4,
+        }
+    }
+
+    /// Params 1-7 of the MAV_CMD_DO_SET_MODE that selects this mode.
+    pub fn command_params(self) -> [f32; 7] {
+        let base_mode = MavModeFlag::MAV_MODE_FLAG_CUSTOM_MODE_ENABLED.bits() as f32;
+        match self {
+            Self::Px4(mode) => [
+                base_mode,
+                mode.main_mode() as f32,
+                mode.sub_mode() as f32,
+                0.0,
+                0.0,
+                0.0,
+                0.0,
+            ],
+            mode => [
+                base_mode,
+                mode.custom_mode() as f32,
+                0.0,
+                0.0,
+                0.0,
+                0.0,
+                0.0,
+            ],
+        }
+    }
+
+    pub fn custom_mode(self) -> u32 {
+        match self {
+            Self::Copter(mode) => mode.custom_mode(),
+            Self::Plane(mode) => mode.custom_mode(),
+            Self::Rover(mode) => mode.custom_mode(),
+            Self::Sub(mode) => mode.custom_mode(),
+            Self::Px4(mode) => mode.custom_mode(),
+        }
+    }
+}
+
+impl fmt::Display for FlightMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Copter(mode) => mode.fmt(f),
+            Self::Plane(mode) => mode.fmt(f),
+            Self::Rover(mode) => mode.fmt(f),
+            Self::Sub(mode) => mode.fmt(f),
+            Self::Px4(mode) => mode.fmt(f),
+        }
+    }
+}
+
+impl Vehicle {
+    /// Switches to `mode` and waits until a HEARTBEAT sent after the ACK
+    /// reports it.
+    ///
+    /// A rejected command is [`Error::CommandFailed`]. If HEARTBEATs keep
+    /// showing another mode the error is [`Error::ModeReverted`], and if
+    /// none arrive it is [`Error::Timeout`].
+    pub fn set_mode(&self, mode: FlightMode) -> Result<()> {
+        self.send_command(MavCmd::MAV_CMD_DO_SET_MODE, mode.command_params())?;
+        let messages = self.subscribe();
+        let deadline = Instant::now() + MODE_CONFIRM_TIMEOUT;
+        let mut reported = None;
+        loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            let Some((header, msg)) = messages.recv_timeout(remaining)? else {
+                break;
+            };
+            match msg {
+                MavMessage::HEARTBEAT(data) if header.component_id == self.component_id() => {
+                    if data.custom_mode == mode.custom_mode() {
+                        return Ok(());
+                    }
+                    reported = Some(data.custom_mode);
+                }
+                _ => {}
+            }
+        }
+        match reported {
+            Some(custom_mode) => Err(Error::ModeReverted {
+                requested: mode,
+                reported: FlightMode::from_custom_mode(mode.family(), custom_mode),
+            }),
+            None => Err(Error::Timeout("HEARTBEAT with the new flight mode")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{
+        Arc,
+        atomic::{AtomicU32, Ordering},
+    };
+
+    use mavlink::ardupilotmega::MavResult;
+
+    use super::*;
+    use crate::mock::{self, command_ack};
+
+    #[test]
+    fn tables_round_trip_through_names_and_numbers() {
+        fn check<T: Copy + PartialEq + fmt::Debug + fmt::Display + FromStr<Err = Error>>(
+            modes: &[T],
+            custom_mode: impl Fn(T) -> u32,
+            from_custom_mode: impl Fn(u32) -> Option<T>,
+        ) {
+            for &mode in modes {
+                assert_eq!(from_custom_mode(custom_mode(mode)), Some(mode));
+                assert_eq!(mode.to_string().to_lowercase().parse::<T>().unwrap(), mode);
+            }
+        }
+        check(
+            CopterMode::ALL,
+            CopterMode::custom_mode,
+            CopterMode::from_custom_mode,
+        );
+        check(
+            PlaneMode::ALL,
+            PlaneMode::custom_mode,
+            PlaneMode::from_custom_mode,
+        );
+        check(
+            RoverMode::ALL,
+            RoverMode::custom_mode,
+            RoverMode::from_custom_mode,
+        );
+        check(
+            SubMode::ALL,
+            SubMode::custom_mode,
+            SubMode::from_custom_mode,
+        );
+        check(
+            Px4Mode::ALL,
+            Px4Mode::custom_mode,
+            Px4Mode::from_custom_mode,
+        );
+    }
+
+    #[test]
+    fn custom_mode_numbers_match_the_autopilots() {
+        assert_eq!(CopterMode::GUIDED.custom_mode(), 4);
+        assert_eq!(PlaneMode::QRTL.custom_mode(), 21);
+        assert_eq!(Px4Mode::AUTO_MISSION.custom_mode(), 0x0404_0000);
+        assert_eq!(
+            (Px4Mode::AUTO_RTL.main_mode(), Px4Mode::AUTO_RTL.sub_mode()),
+            (4, 5)
+        );
+        assert_eq!(CopterMode::from_custom_mode(8), None);
+    }
+
+    #[test]
+    fn parse_uses_the_family_table() {
+        assert_eq!(
+            FlightMode::parse(ModeFamily::Plane, "fbwa").unwrap(),
+            FlightMode::Plane(PlaneMode::FBWA)
+        );
+        assert!(matches!(
+            FlightMode::parse(ModeFamily::Copter, "FBWA"),
+            Err(Error::InvalidMode(_))
+        ));
+        assert_eq!(
+            FlightMode::from_custom_mode(ModeFamily::Rover, 4),
+            Some(FlightMode::Rover(RoverMode::HOLD))
+        );
+    }
+
+    #[test]
+    fn families_come_from_the_heartbeat() {
+        let heartbeat = HEARTBEAT_DATA {
+            custom_mode: 10,
+            mavtype: MavType::MAV_TYPE_FIXED_WING,
+            autopilot: MavAutopilot::MAV_AUTOPILOT_ARDUPILOTMEGA,
+            ..HEARTBEAT_DATA::default()
+        };
+        assert_eq!(
+            FlightMode::from_heartbeat(&heartbeat),
+            Some(FlightMode::Plane(PlaneMode::AUTO))
+        );
+        let gcs = HEARTBEAT_DATA {
+            mavtype: MavType::MAV_TYPE_GCS,
+            ..heartbeat
+        };
+        assert_eq!(ModeFamily::from_heartbeat(&gcs), None);
+    }
+    #[test]
+    fn command_params_split_px4_modes() {
+        let params = FlightMode::Px4(Px4Mode::AUTO_LOITER).command_params();
+        assert_eq!(params[..3], [1.0, 4.0, 3.0]);
+        let params = FlightMode::Copter(CopterMode::LOITER).command_params();
+        assert_eq!(params[..3], [1.0, 5.0, 0.0]);
+    }
+
+    /// What the mock copter's HEARTBEATs show after a mode change.
+    #[derive(Clone, Copy, PartialEq)]
+    enum Heartbeats {
+        NewMode,
+        OldMode,
+        Silent,
+    }
+
+    fn copter(result: MavResult, heartbeats: Heartbeats) -> Vehicle {
+        let custom_mode = Arc::new(AtomicU32::new(CopterMode::STABILIZE.custom_mode()));
+        let reported = custom_mode.clone();
+        let (vehicle, peer) = mock::vehicle(move |msg| match msg {
+            MavMessage::COMMAND_LONG(data) if data.command == MavCmd::MAV_CMD_DO_SET_MODE => {
+                if heartbeats == Heartbeats::NewMode {
+                    custom_mode.store(data.param2 as u32, Ordering::Relaxed);
+                }
+                vec![command_ack(data.command, result)]
+            }
+            _ => Vec::new(),
+        });
+        if heartbeats != Heartbeats::Silent {
+            peer.send_heartbeats(move || HEARTBEAT_DATA {
+                custom_mode: reported.load(Ordering::Relaxed),
+                ..Default::default()
+            });
+        }
+        vehicle
+    }
+
+    const GUIDED: FlightMode = FlightMode::Copter(CopterMode::GUIDED);
+
+    #[test]
+    fn set_mode_waits_for_the_heartbeat() {
+        let vehicle = copter(MavResult::MAV_RESULT_ACCEPTED, Heartbeats::NewMode);
+        vehicle.set_mode(GUIDED).unwrap();
+    }
+
+    #[test]
+    fn set_mode_reports_a_rejection() {
+        let vehicle = copter(MavResult::MAV_RESULT_DENIED, Heartbeats::NewMode);
+        assert!(matches!(
+            vehicle.set_mode(GUIDED),
+            Err(Error::CommandFailed {
+                result: MavResult::MAV_RESULT_DENIED,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn set_mode_reports_the_mode_the_vehicle_kept() {
+        let vehicle = copter(MavResult::MAV_RESULT_ACCEPTED, Heartbeats::OldMode);
+        assert!(matches!(
+            vehicle.set_mode(GUIDED),
+            Err(Error::ModeReverted {
+                requested: GUIDED,
+                reported: Some(FlightMode::Copter(CopterMode::STABILIZE)),
+            })
+        ));
+    }
+
+    #[test]
+    fn set_mode_times_out_without_heartbeats() {
+        let vehicle = copter(MavResult::MAV_RESULT_ACCEPTED, Heartbeats::Silent);
+        assert!(matches!(vehicle.set_mode(GUIDED), Err(Error::Timeout(_))));
+    }
+}
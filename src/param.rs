@@ -0,0 +1,745 @@
+use std::{collections::BTreeMap, fmt, str::FromStr, time::Duration};
+
+use mavlink::ardupilotmega::{
+    MavMessage, MavParamType, MavProtocolCapability, PARAM_REQUEST_LIST_DATA,
+    PARAM_REQUEST_READ_DATA, PARAM_SET_DATA, PARAM_VALUE_DATA,
+};
+
+use crate::{
+    error::{Error, Result},
+    station::Subscription,
+    vehicle::Vehicle,
+};
+
+const PARAM_TIMEOUT: Duration = Duration::from_secs(3);
+/// A download that stays silent this long has finished streaming or lost values.
+const PARAM_STREAM_IDLE: Duration = Duration::from_secs(1);
+/// Rounds of re-requests that may go by without a single new value, and
+/// resends of an unanswered PARAM_SET.
+const PARAM_RETRIES: u8 = 3;
+/// The param_index of a PARAM_VALUE that is not part of a listing, e.g. a PARAM_SET echo.
+const UNINDEXED: u16 = u16::MAX;
+
+/// A parameter as reported by PARAM_VALUE.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Param {
+    pub id: ParamId,
+    pub value: ParamValue,
+    pub index: u16,
+    pub count: u16,
+}
+
+impl Param {
+    fn decode(data: &PARAM_VALUE_DATA, encoding: ParamEncoding) -> Result<Self> {
+        Ok(Self {
+            id: ParamId::from_bytes(&data.param_id)?,
+            value: ParamValue::decode(data.param_value, data.param_type, encoding),
+            index: data.param_index,
+            count: data.param_count,
+        })
+    }
+}
+
+/// A parameter value in the type the autopilot stores it as.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ParamValue {
+    U8(u8),
+    I8(i8),
+    U16(u16),
+    I16(i16),
+    U32(u32),
+    I32(i32),
+    F32(f32),
+}
+
+impl ParamValue {
+    /// Unpacks the float carried by PARAM_VALUE. The 64-bit types do not
+    /// fit in it and are kept as the raw float.
+    pub fn decode(raw: f32, param_type: MavParamType, encoding: ParamEncoding) -> Self {
+        let bits = raw.to_bits();
+        match (encoding, param_type) {
+            (ParamEncoding::Bytewise, MavParamType::MAV_PARAM_TYPE_UINT8) => Self::U8(bits as u8),
+            (ParamEncoding::Bytewise, MavParamType::MAV_PARAM_TYPE_INT8) => Self::I8(bits as i8),
+            (ParamEncoding::Bytewise, MavParamType::MAV_PARAM_TYPE_UINT16) => {
+                Self::U16(bits as u16)
+            }
+            (ParamEncoding::Bytewise, MavParamType::MAV_PARAM_TYPE_INT16) => Self::I16(bits as i16),
+            (ParamEncoding::Bytewise, MavParamType::MAV_PARAM_TYPE_UINT32) => Self::U32(bits),
+            (ParamEncoding::Bytewise, MavParamType::MAV_PARAM_TYPE_INT32) => Self::I32(bits as i32),
+            (ParamEncoding::CCast, MavParamType::MAV_PARAM_TYPE_UINT8) => Self::U8(raw as u8),
+            (ParamEncoding::CCast, MavParamType::MAV_PARAM_TYPE_INT8) => Self::I8(raw as i8),
+            (ParamEncoding::CCast, MavParamType::MAV_PARAM_TYPE_UINT16) => Self::U16(raw as u16),
+            (ParamEncoding::CCast, MavParamType::MAV_PARAM_TYPE_INT16) => Self::I16(raw as i16),
+            (ParamEncoding::CCast, MavParamType::MAV_PARAM_TYPE_UINT32) => Self::U32(raw as u32),
+            (ParamEncoding::CCast, MavParamType::MAV_PARAM_TYPE_INT32) => Self::I32(raw as i32),
+            _ => Self::F32(raw),
+        }
+    }
+
+    /// Packs the value into the float sent in PARAM_SET.
+    pub fn encode(self, encoding: ParamEncoding) -> f32 {
+        match encoding {
+            ParamEncoding::Bytewise => f32::from_bits(match self {
+                Self::U8(value) => value as u32,
+                Self::I8(value) => value as u8 as u32,
+                Self::U16(value) => value as u32,
+                Self::I16(value) => value as u16 as u32,
+                Self::U32(value) => value,
+                Self::I32(value) => value as u32,
+                Self::F32(value) => return value,
+            }),
+            ParamEncoding::CCast => self.as_f64() as f32,
+        }
+    }
+
+    /// Converts `value` to `param_type`, or `None` if that type cannot hold it exactly.
+    pub fn with_type(param_type: MavParamType, value: f64) -> Option<Self> {
+        let integer = (value.fract() == 0.0).then_some(value as i64);
+        Some(match param_type {
+            MavParamType::MAV_PARAM_TYPE_UINT8 => Self::U8(integer?.try_into().ok()?),
+            MavParamType::MAV_PARAM_TYPE_INT8 => Self::I8(integer?.try_into().ok()?),
+            MavParamType::MAV_PARAM_TYPE_UINT16 => Self::U16(integer?.try_into().ok()?),
+            MavParamType::MAV_PARAM_TYPE_INT16 => Self::I16(integer?.try_into().ok()?),
+            MavParamType::MAV_PARAM_TYPE_UINT32 => Self::U32(integer?.try_into().ok()?),
+            MavParamType::MAV_PARAM_TYPE_INT32 => Self::I32(integer?.try_into().ok()?),
+            _ => Self::F32(value as f32),
+        })
+    }
+
+    pub fn param_type(self) -> MavParamType {
+        match self {
+            Self::U8(_) => MavParamType::MAV_PARAM_TYPE_UINT8,
+            Self::I8(_) => MavParamType::MAV_PARAM_TYPE_INT8,
+            Self::U16(_) => MavParamType::MAV_PARAM_TYPE_UINT16,
+            Self::I16(_) => MavParamType::MAV_PARAM_TYPE_INT16,
+            Self::U32(_) => MavParamType::MAV_PARAM_TYPE_UINT32,
+            Self::I32(_) => MavParamType::MAV_PARAM_TYPE_INT32,
+            Self::F32(_) => MavParamType::MAV_PARAM_TYPE_REAL32,
+        }
+    }
+
+    pub fn as_f64(self) -> f64 {
+        match self {
+            Self::U8(value) => value.into(),
+            Self::I8(value) => value.into(),
+            Self::U16(value) => value.into(),
+            Self::I16(value) => value.into(),
+            Self::U32(value) => value.into(),
+            Self::I32(value) => value.into(),
+            Self::F32(value) => value.into(),
+        }
+    }
+}
+
+impl fmt::Display for ParamValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::U8(value) => value.fmt(f),
+            Self::I8(value) => value.fmt(f),
+            Self::U16(value) => value.fmt(f),
+            Self::I16(value) => value.fmt(f),
+            Self::U32(value) => value.fmt(f),
+            Self::I32(value) => value.fmt(f),
+            Self::F32(value) => value.fmt(f),
+        }
+    }
+}
+
+/// How integer parameters travel in the float field of PARAM_VALUE/PARAM_SET.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParamEncoding {
+    /// The integer's bytes are copied into the float, as PX4 does.
+    Bytewise,
+    /// The integer is converted to the nearest float, as ArduPilot does.
+    CCast,
+}
+
+impl ParamEncoding {
+    /// Autopilots that claim neither encoding are assumed to C-cast.
+    pub fn from_capabilities(capabilities: MavProtocolCapability) -> Self {
+        if capabilities
+            .contains(MavProtocolCapability::MAV_PROTOCOL_CAPABILITY_PARAM_ENCODE_BYTEWISE)
+        {
+            Self::Bytewise
+        } else {
+            Self::CCast
+        }
+    }
+}
+
+impl Vehicle {
+    /// Downloads every parameter, ordered by index.
+    pub fn list_params(&self) -> Result<ParamTable> {
+        self.list_params_with_progress(|_| {})
+    }
+
+    /// Like [`Vehicle::list_params`], passing the fraction received so far
+    /// (0.0 to 1.0) to `on_progress` after every new value.
+    ///
+    /// Indices still missing once the stream goes quiet are re-requested one
+    /// by one with PARAM_REQUEST_READ. Values whose name is not a valid
+    /// [`ParamId`] are left out and listed in [`ParamTable::skipped`].
+    pub fn list_params_with_progress(
+        &self,
+        mut on_progress: impl FnMut(f32),
+    ) -> Result<ParamTable> {
+        let encoding = self.param_encoding()?;
+        let messages = self.subscribe();
+        let mut params = BTreeMap::new();
+        let mut count = None;
+        let mut retries = 0;
+        self.request_param_list()?;
+        loop {
+            let received =
+                messages.wait_for(PARAM_STREAM_IDLE, "PARAM_VALUE", |header, msg| match msg {
+                    MavMessage::PARAM_VALUE(data)
+                        if header.component_id == self.component_id()
+                            && data.param_index != UNINDEXED =>
+                    {
+                        Some(data.clone())
+                    }
+                    _ => None,
+                });
+            match received {
+                Ok(data) => {
+                    count = Some(data.param_count);
+                    let param = Param::decode(&data, encoding).ok();
+                    if params.insert(data.param_index, param).is_none() {
+                        retries = 0;
+                        on_progress(params.len() as f32 / data.param_count.max(1) as f32);
+                    }
+                    if params.len() >= data.param_count as usize {
+                        return Ok(ParamTable {
+                            system_id: self.system_id(),
+                            component_id: self.component_id(),
+                            skipped: params
+                                .iter()
+                                .filter(|(_, param)| param.is_none())
+                                .map(|(index, _)| *index)
+                                .collect(),
+                            params: params.into_values().flatten().collect(),
+                        });
+                    }
+                    continue;
+                }
+                Err(Error::Timeout(_)) => {}
+                Err(e) => return Err(e),
+            }
+            if retries == PARAM_RETRIES {
+                return Err(Error::Timeout("PARAM_VALUE"));
+            }
+            retries += 1;
+            match count {
+                None => self.request_param_list()?,
+                Some(count) => {
+                    for index in (0..count).filter(|index| !params.contains_key(index)) {
+                        self.request_param_by_index(index)?;
+                    }
+                }
+            }
+        }
+    }
+
+    pub fn read_param(&self, id: &ParamId) -> Result<Param> {
+        let encoding = self.param_encoding()?;
+        let messages = self.subscribe();
+        self.send(&MavMessage::PARAM_REQUEST_READ(PARAM_REQUEST_READ_DATA {
+            target_system: self.system_id(),
+            target_component: self.component_id(),
+            param_id: id.to_bytes(),
+            param_index: -1,
+        }))?;
+        wait_for_param(&messages, id, encoding)
+    }
+
+    /// Reads the parameter to learn its type, then writes `value` as that
+    /// type, confirmed as in [`Vehicle::write_param`]. Values the type
+    /// cannot hold exactly are refused, as are values outside the range
+    /// documented in the [`Vehicle::with_param_metadata`] database.
+    pub fn set_param(&self, id: &ParamId, value: f64) -> Result<Param> {
+        if let Some(meta) = self.param_metadata().and_then(|db| db.get(id))
+            && let Some((min, max)) = meta.range
+            && !meta.in_range(value)
+        {
+            return Err(Error::OutOfRange {
+                id: id.clone(),
+                value,
+                min,
+                max,
+            });
+        }
+        self.force_set_param(id, value)
+    }
+
+    /// Like [`Vehicle::set_param`], but skips the documented range check.
+    pub fn force_set_param(&self, id: &ParamId, value: f64) -> Result<Param> {
+        let param_type = self.read_param(id)?.value.param_type();
+        let value = ParamValue::with_type(param_type, value).ok_or_else(|| {
+            Error::InvalidParam(format!("{id} cannot hold {value} as {param_type:?}"))
+        })?;
+        self.write_param(id, value)
+    }
+
+    /// Writes `value` with its own type and waits for the vehicle to echo it.
+    ///
+    /// PARAM_SET is resent while no echo arrives. An echo holding anything
+    /// else, e.g. because the autopilot clamped the value, is returned as
+    /// [`Error::ParamMismatch`].
+    pub fn write_param(&self, id: &ParamId, value: ParamValue) -> Result<Param> {
+        let encoding = self.param_encoding()?;
+        let messages = self.subscribe();
+        for _ in 0..=PARAM_RETRIES {
+            self.send(&MavMessage::PARAM_SET(PARAM_SET_DATA {
+                target_system: self.system_id(),
+                target_component: self.component_id(),
+                param_id: id.to_bytes(),
+                param_value: value.encode(encoding),
+                param_type: value.param_type(),
+            }))?;
+            match wait_for_param(&messages, id, encoding) {
+                Ok(param) if param.value == value => return Ok(param),
+                Ok(param) => {
+                    return Err(Error::ParamMismatch {
+                        id: param.id,
+                        requested: value,
+                        echoed: param.value,
+                    });
+                }
+                Err(Error::Timeout(_)) => continue,
+                Err(e) => return Err(e),
+            }
+        }
+        Err(Error::Timeout("PARAM_VALUE"))
+    }
+
+    pub fn param_encoding(&self) -> Result<ParamEncoding> {
+        Ok(ParamEncoding::from_capabilities(self.capabilities()?))
+    }
+
+    fn request_param_list(&self) -> Result<()> {
+        self.send(&MavMessage::PARAM_REQUEST_LIST(PARAM_REQUEST_LIST_DATA {
+            target_system: self.system_id(),
+            target_component: self.component_id(),
+        }))
+    }
+
+    fn request_param_by_index(&self, index: u16) -> Result<()> {
+        self.send(&MavMessage::PARAM_REQUEST_READ(PARAM_REQUEST_READ_DATA {
+            target_system: self.system_id(),
+            target_component: self.component_id(),
+            param_id: [0; 16],
+            param_index: index as i16,
+        }))
+    }
+}
+
+/// Every parameter of a vehicle, ordered by index.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ParamTable {
+    system_id: u8,
+    component_id: u8,
+    params: Vec<Param>,
+    skipped: Vec<u16>,
+}
+
+impl ParamTable {
+    /// The system the parameters were downloaded from.
+    pub fn system_id(&self) -> u8 {
+        self.system_id
+    }
+
+    pub fn component_id(&self) -> u8 {
+        self.component_id
+    }
+
+    pub fn get(&self, id: &ParamId) -> Option<&Param> {
+        self.params.iter().find(|param| param.id == *id)
+    }
+
+    /// Indices the vehicle sent with a name that is not a valid [`ParamId`].
+    pub fn skipped(&self) -> &[u16] {
+        &self.skipped
+    }
+
+    pub fn len(&self) -> usize {
+        self.params.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.params.is_empty()
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, Param> {
+        self.params.iter()
+    }
+}
+
+impl IntoIterator for ParamTable {
+    type Item = Param;
+    type IntoIter = std::vec::IntoIter<Param>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.params.into_iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a ParamTable {
+    type Item = &'a Param;
+    type IntoIter = std::slice::Iter<'a, Param>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.params.iter()
+    }
+}
+
+fn wait_for_param(messages: &Subscription, id: &ParamId, encoding: ParamEncoding) -> Result<Param> {
+    messages.wait_for(PARAM_TIMEOUT, "PARAM_VALUE", |_, msg| match msg {
+        MavMessage::PARAM_VALUE(data) => Param::decode(data, encoding)
+            .ok()
+            .filter(|param| param.id == *id),
+        _ => None,
+    })
+}
+
+/// A parameter name: 1 to 16 printable ASCII characters.
+///
+/// On the wire names are NUL-padded to 16 bytes; a name of exactly 16
+/// characters has no terminator.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ParamId(String);
+
+impl ParamId {
+    pub const MAX_LEN: usize = 16;
+
+    pub fn new(id: &str) -> Result<Self> {
+        if id.is_empty() {
+            return Err(Error::InvalidParam("empty parameter name".to_string()));
+        }
+        if let Some(c) = id.chars().find(|c| !c.is_ascii_graphic()) {
+            return Err(Error::InvalidParam(format!(
+                "{id:?} contains {c:?}; names are printable ASCII"
+            )));
+        }
+        if id.len() > Self::MAX_LEN {
+            return Err(Error::InvalidParam(format!(
+                "{id} is {} characters long, the limit is {}",
+                id.len(),
+                Self::MAX_LEN
+            )));
+        }
+        Ok(Self(id.to_string()))
+    }
+
+    /// Decodes a wire name, which ends at the first NUL or after 16 bytes,
+    /// with the same checks as [`ParamId::new`].
+    pub fn from_bytes(bytes: &[u8; 16]) -> Result<Self> {
+        let len = bytes.iter().position(|&b| b == 0).unwrap_or(bytes.len());
+        Self::new(&String::from_utf8_lossy(&bytes[..len]))
+    }
+
+    pub fn to_bytes(&self) -> [u8; 16] {
+        let mut bytes = [0u8; 16];
+        for (byte, c) in bytes.iter_mut().zip(self.0.bytes()) {
+            *byte = c;
+        }
+        bytes
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl FromStr for ParamId {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Self::new(s)
+    }
+}
+
+impl fmt::Display for ParamId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad(&self.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use mavlink::ardupilotmega::{AUTOPILOT_VERSION_DATA, MavCmd, MavResult};
+
+    use super::*;
+    use crate::mock::{self, MockPeer, command_ack};
+
+    /// A C-casting autopilot that answers the capability request itself
+    /// and everything else with `answer`.
+    fn autopilot(
+        mut answer: impl FnMut(&MavMessage) -> Vec<MavMessage> + Send + 'static,
+    ) -> (Vehicle, MockPeer) {
+        mock::vehicle(move |msg| match msg {
+            MavMessage::COMMAND_LONG(data) if data.command == MavCmd::MAV_CMD_REQUEST_MESSAGE => {
+                vec![
+                    command_ack(data.command, MavResult::MAV_RESULT_ACCEPTED),
+                    MavMessage::AUTOPILOT_VERSION(AUTOPILOT_VERSION_DATA::default()),
+                ]
+            }
+            msg => answer(msg),
+        })
+    }
+
+    fn param_value(id: &str, value: ParamValue, index: u16, count: u16) -> MavMessage {
+        MavMessage::PARAM_VALUE(PARAM_VALUE_DATA {
+            param_id: ParamId::new(id).unwrap().to_bytes(),
+            param_value: value.encode(ParamEncoding::CCast),
+            param_type: value.param_type(),
+            param_index: index,
+            param_count: count,
+        })
+    }
+
+    const NAMES: [&str; 4] = ["ACRO_RP_RATE", "ANGLE_MAX", "BATT_MONITOR", "RTL_ALT"];
+
+    /// The autopilot's reply to a PARAM_REQUEST_LIST or a read by index,
+    /// leaving out the indices in `dropped`.
+    fn stream(msg: &MavMessage, dropped: &[u16]) -> Vec<MavMessage> {
+        let indices = match msg {
+            MavMessage::PARAM_REQUEST_LIST(_) => 0..NAMES.len() as u16,
+            MavMessage::PARAM_REQUEST_READ(data) => {
+                data.param_index as u16..data.param_index as u16 + 1
+            }
+            _ => return Vec::new(),
+        };
+        indices
+            .filter(|index| !dropped.contains(index))
+            .map(|index| {
+                let value = ParamValue::F32(index as f32);
+                param_value(NAMES[index as usize], value, index, NAMES.len() as u16)
+            })
+            .collect()
+    }
+
+    fn reads_by_index(peer: &MockPeer) -> Vec<i16> {
+        peer.sent()
+            .iter()
+            .filter_map(|msg| match msg {
+                MavMessage::PARAM_REQUEST_READ(data) => Some(data.param_index),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn list_params_re_requests_missing_indices() {
+        let first_listing = Arc::new(Mutex::new(true));
+        let (vehicle, peer) = autopilot(move |msg| {
+            let dropped: &[u16] = if std::mem::take(&mut *first_listing.lock().unwrap()) {
+                &[1, 3]
+            } else {
+                &[]
+            };
+            stream(msg, dropped)
+        });
+        let mut progress = Vec::new();
+        let table = vehicle
+            .list_params_with_progress(|fraction| progress.push(fraction))
+            .unwrap();
+        let names: Vec<_> = table.iter().map(|param| param.id.as_str()).collect();
+        assert_eq!(names, NAMES);
+        assert_eq!(
+            table.get(&ParamId::new("RTL_ALT").unwrap()).unwrap().index,
+            3
+        );
+        assert_eq!(reads_by_index(&peer), [1, 3]);
+        assert_eq!(progress, [0.25, 0.5, 0.75, 1.0]);
+    }
+
+    #[test]
+    fn list_params_gives_up_after_the_retry_budget() {
+        let (vehicle, peer) = autopilot(|msg| stream(msg, &[2]));
+        assert!(matches!(
+            vehicle.list_params(),
+            Err(Error::Timeout("PARAM_VALUE"))
+        ));
+        assert_eq!(reads_by_index(&peer), [2; PARAM_RETRIES as usize]);
+    }
+
+    #[test]
+    fn list_params_skips_invalid_names() {
+        let (vehicle, peer) = autopilot(|msg| {
+            let mut values = stream(msg, &[]);
+            if let Some(MavMessage::PARAM_VALUE(data)) = values.get_mut(1) {
+                data.param_id = [0xC4; 16];
+            }
+            values
+        });
+        let table = vehicle.list_params().unwrap();
+        let names: Vec<_> = table.iter().map(|param| param.id.as_str()).collect();
+        assert_eq!(names, ["ACRO_RP_RATE", "BATT_MONITOR", "RTL_ALT"]);
+        assert_eq!(table.skipped(), [1]);
+        assert!(reads_by_index(&peer).is_empty());
+    }
+
+    /// An autopilot holding WPNAV_SPEED as an INT16 that clamps writes to
+    /// `max` and ignores the first `ignored` PARAM_SETs.
+    fn int16_autopilot(max: i16, mut ignored: usize) -> (Vehicle, MockPeer) {
+        let mut value = 500;
+        autopilot(move |msg| match msg {
+            MavMessage::PARAM_REQUEST_READ(_) => {
+                vec![param_value("WPNAV_SPEED", ParamValue::I16(value), 7, 9)]
+            }
+            MavMessage::PARAM_SET(_) if ignored > 0 => {
+                ignored -= 1;
+                Vec::new()
+            }
+            MavMessage::PARAM_SET(data) => {
+                value = (data.param_value as i16).min(max);
+                vec![param_value(
+                    "WPNAV_SPEED",
+                    ParamValue::I16(value),
+                    UNINDEXED,
+                    9,
+                )]
+            }
+            _ => Vec::new(),
+        })
+    }
+
+    fn param_sets(peer: &MockPeer) -> Vec<(f32, MavParamType)> {
+        peer.sent()
+            .iter()
+            .filter_map(|msg| match msg {
+                MavMessage::PARAM_SET(data) => Some((data.param_value, data.param_type)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn set_param_writes_the_type_it_read() {
+        let (vehicle, peer) = int16_autopilot(2000, 0);
+        let id = ParamId::new("WPNAV_SPEED").unwrap();
+        let param = vehicle.set_param(&id, 750.0).unwrap();
+        assert_eq!(param.value, ParamValue::I16(750));
+        assert_eq!(
+            param_sets(&peer),
+            [(750.0, MavParamType::MAV_PARAM_TYPE_INT16)]
+        );
+        assert!(matches!(
+            vehicle.set_param(&id, 750.5),
+            Err(Error::InvalidParam(_))
+        ));
+    }
+
+    #[test]
+    fn write_param_reports_a_clamped_echo() {
+        let (vehicle, _peer) = int16_autopilot(1000, 0);
+        let id = ParamId::new("WPNAV_SPEED").unwrap();
+        match vehicle.write_param(&id, ParamValue::I16(1500)) {
+            Err(Error::ParamMismatch {
+                id: echoed_id,
+                requested,
+                echoed,
+            }) => {
+                assert_eq!(echoed_id, id);
+                assert_eq!(requested, ParamValue::I16(1500));
+                assert_eq!(echoed, ParamValue::I16(1000));
+            }
+            other => panic!("write_param returned {other:?}"),
+        }
+    }
+
+    #[test]
+    fn write_param_resends_until_echoed() {
+        let (vehicle, peer) = int16_autopilot(2000, 1);
+        let id = ParamId::new("WPNAV_SPEED").unwrap();
+        vehicle.write_param(&id, ParamValue::I16(600)).unwrap();
+        assert_eq!(param_sets(&peer).len(), 2);
+    }
+
+    const ALL_TYPES: [ParamValue; 7] = [
+        ParamValue::U8(200),
+        ParamValue::I8(-100),
+        ParamValue::U16(60_000),
+        ParamValue::I16(-30_000),
+        ParamValue::U32(4_000_000_000),
+        ParamValue::I32(-2_000_000_000),
+        ParamValue::F32(0.25),
+    ];
+
+    #[test]
+    fn param_values_round_trip_in_both_encodings() {
+        for encoding in [ParamEncoding::Bytewise, ParamEncoding::CCast] {
+            for value in ALL_TYPES {
+                let raw = value.encode(encoding);
+                assert_eq!(
+                    ParamValue::decode(raw, value.param_type(), encoding),
+                    value,
+                    "{encoding:?}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn encodings_differ_for_integers() {
+        assert_eq!(ParamValue::I32(-1).encode(ParamEncoding::CCast), -1.0);
+        assert_eq!(
+            ParamValue::I32(-1)
+                .encode(ParamEncoding::Bytewise)
+                .to_bits(),
+            u32::MAX
+        );
+        assert_eq!(
+            ParamValue::I8(-1).encode(ParamEncoding::Bytewise).to_bits(),
+            0xFF
+        );
+        assert_eq!(ParamValue::F32(1.5).encode(ParamEncoding::Bytewise), 1.5);
+    }
+
+    #[test]
+    fn with_type_rejects_values_the_type_cannot_hold() {
+        use MavParamType::*;
+        assert_eq!(
+            ParamValue::with_type(MAV_PARAM_TYPE_INT8, -5.0),
+            Some(ParamValue::I8(-5))
+        );
+        assert_eq!(ParamValue::with_type(MAV_PARAM_TYPE_UINT8, 256.0), None);
+        assert_eq!(ParamValue::with_type(MAV_PARAM_TYPE_UINT16, -1.0), None);
+        assert_eq!(ParamValue::with_type(MAV_PARAM_TYPE_INT32, 1.5), None);
+        assert_eq!(
+            ParamValue::with_type(MAV_PARAM_TYPE_REAL32, 1.5),
+            Some(ParamValue::F32(1.5))
+        );
+    }
+
+    #[test]
+    fn param_id_round_trips_through_wire_bytes() {
+        for name in ["A", "SYSID_THISMAV", "COMPASS_OFS2_XYZ"] {
+            let id = ParamId::new(name).unwrap();
+            assert_eq!(ParamId::from_bytes(&id.to_bytes()).unwrap(), id);
+        }
+        let bytes = ParamId::new("COMPASS_OFS2_XYZ").unwrap().to_bytes();
+        assert_eq!(&bytes, b"COMPASS_OFS2_XYZ");
+    }
+
+    #[test]
+    fn param_id_rejects_invalid_names() {
+        assert!(ParamId::new("").is_err());
+        assert!(ParamId::new("COMPASS_OFS2_XYZW").is_err());
+        assert!(ParamId::new("HAS SPACE").is_err());
+        assert!(ParamId::new("ÄNGLE").is_err());
+    }
+
+    #[test]
+    fn param_id_rejects_invalid_wire_bytes() {
+        assert!(ParamId::from_bytes(&[0; 16]).is_err());
+        assert!(ParamId::from_bytes(&[0xC4; 16]).is_err());
+        let mut bytes = *b"ANGLE_MAX\0\0\0\0\0\0\0";
+        bytes[2] = 0x80;
+        assert!(ParamId::from_bytes(&bytes).is_err());
+        bytes[2] = b'G';
+        assert_eq!(ParamId::from_bytes(&bytes).unwrap().as_str(), "ANGLE_MAX");
+    }
+}
@@ -0,0 +1,372 @@
+use std::{
+    fmt::{self, Write as _},
+    fs,
+    path::Path,
+};
+
+use crate::{
+    error::{Error, Result},
+    param::{ParamId, ParamTable, ParamValue},
+    vehicle::Vehicle,
+};
+
+/// The layout of a parameter file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParamFileFormat {
+    /// MAVProxy/Mission Planner: one `NAME VALUE` per line, `#` comments.
+    /// Commas are accepted as the separator too.
+    Parm,
+    /// QGroundControl: tab-separated `SYSID COMPID NAME VALUE TYPE`.
+    Qgc,
+}
+
+impl ParamFileFormat {
+    /// `.params` files are QGroundControl's; anything else is read as `.parm`.
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension() {
+            Some(extension) if extension.eq_ignore_ascii_case("params") => Self::Qgc,
+            _ => Self::Parm,
+        }
+    }
+}
+
+/// Parameter values loaded from a file, in file order.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ParamFile {
+    params: Vec<(ParamId, f64)>,
+}
+
+impl ParamFile {
+    /// Reads `path` in the format its extension implies.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let text = fs::read_to_string(path).map_err(Error::Io)?;
+        Self::parse(&text, ParamFileFormat::from_path(path))
+    }
+
+    pub fn parse(text: &str, format: ParamFileFormat) -> Result<Self> {
+        let mut file = Self::default();
+        for (number, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let invalid = |message: String| Error::InvalidParamFile {
+                line: number + 1,
+                message,
+            };
+            let fields: Vec<&str> = match format {
+                ParamFileFormat::Parm => line
+                    .split(|c: char| c == ',' || c.is_whitespace())
+                    .filter(|field| !field.is_empty())
+                    .collect(),
+                ParamFileFormat::Qgc => line.split('\t').map(str::trim).collect(),
+            };
+            let (id, value) = match (format, fields.as_slice()) {
+                (ParamFileFormat::Parm, [id, value]) => (id, value),
+                (ParamFileFormat::Qgc, [_, _, id, value, _]) => (id, value),
+                (ParamFileFormat::Parm, _) => {
+                    return Err(invalid("expected NAME VALUE".to_string()));
+                }
+                (ParamFileFormat::Qgc, _) => {
+                    return Err(invalid(
+                        "expected 5 tab-separated fields: SYSID COMPID NAME VALUE TYPE".to_string(),
+                    ));
+                }
+            };
+            let id = ParamId::new(id).map_err(|e| invalid(e.to_string()))?;
+            let value = value
+                .parse()
+                .map_err(|_| invalid(format!("{value} is not a number")))?;
+            file.insert(id, value);
+        }
+        Ok(file)
+    }
+
+    pub fn get(&self, id: &ParamId) -> Option<f64> {
+        self.params
+            .iter()
+            .find(|(param_id, _)| param_id == id)
+            .map(|(_, value)| *value)
+    }
+
+    /// Sets `id`, keeping its position if the file already has it.
+    pub fn insert(&mut self, id: ParamId, value: f64) {
+        match self.params.iter_mut().find(|(param_id, _)| *param_id == id) {
+            Some((_, old)) => *old = value,
+            None => self.params.push((id, value)),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.params.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.params.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&ParamId, f64)> + '_ {
+        self.params.iter().map(|(id, value)| (id, *value))
+    }
+
+    /// Lists what changes going from `self` to `other`, ordered by name.
+    /// Values within `tolerance` of each other, relative to the larger one
+    /// (or absolute below 1), count as equal.
+    pub fn diff(&self, other: &ParamFile, tolerance: f64) -> Vec<ParamChange> {
+        let mut changes = Vec::new();
+        for (id, from) in self.iter() {
+            match other.get(id) {
+                None => changes.push(ParamChange::Removed {
+                    id: id.clone(),
+                    value: from,
+                }),
+                Some(to) if !approx_eq(from, to, tolerance) => changes.push(ParamChange::Changed {
+                    id: id.clone(),
+                    from,
+                    to,
+                }),
+                Some(_) => {}
+            }
+        }
+        for (id, value) in other.iter() {
+            if self.get(id).is_none() {
+                changes.push(ParamChange::Added {
+                    id: id.clone(),
+                    value,
+                });
+            }
+        }
+        changes.sort_by(|a, b| a.id().cmp(b.id()));
+        changes
+    }
+}
+
+/// Relative tolerance that absorbs the rounding of values stored as f32 and
+/// printed with few decimals.
+pub const DIFF_TOLERANCE: f64 = 1e-5;
+
+/// One difference found by [`ParamFile::diff`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum ParamChange {
+    /// Only in the second set.
+    Added {
+        id: ParamId,
+        value: f64,
+    },
+    /// Only in the first set.
+    Removed {
+        id: ParamId,
+        value: f64,
+    },
+    Changed {
+        id: ParamId,
+        from: f64,
+        to: f64,
+    },
+}
+
+impl ParamChange {
+    pub fn id(&self) -> &ParamId {
+        match self {
+            Self::Added { id, .. } | Self::Removed { id, .. } | Self::Changed { id, .. } => id,
+        }
+    }
+}
+
+impl fmt::Display for ParamChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Added { id, value } => write!(f, "+ {id:<16} {value}"),
+            Self::Removed { id, value } => write!(f, "- {id:<16} {value}"),
+            Self::Changed { id, from, to } => write!(f, "~ {id:<16} {from} -> {to}"),
+        }
+    }
+}
+
+fn approx_eq(a: f64, b: f64, tolerance: f64) -> bool {
+    (a - b).abs() <= tolerance * a.abs().max(b.abs()).max(1.0)
+}
+
+impl From<&ParamTable> for ParamFile {
+    fn from(table: &ParamTable) -> Self {
+        Self {
+            params: table
+                .iter()
+                .map(|param| (param.id.clone(), param.value.as_f64()))
+                .collect(),
+        }
+    }
+}
+
+impl ParamTable {
+    /// Writes the table to `path` in the format its extension implies.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        fs::write(path, self.to_file_text(ParamFileFormat::from_path(path))).map_err(Error::Io)
+    }
+
+    pub fn to_file_text(&self, format: ParamFileFormat) -> String {
+        let mut text = String::new();
+        match format {
+            ParamFileFormat::Parm => {
+                for param in self {
+                    let _ = writeln!(text, "{:<16} {}", param.id, param.value);
+                }
+            }
+            ParamFileFormat::Qgc => {
+                let _ = writeln!(
+                    text,
+                    "# Onboard parameters for Vehicle {}",
+                    self.system_id()
+                );
+                let _ = writeln!(text, "#");
+                let _ = writeln!(text, "# Vehicle-Id Component-Id Name Value Type");
+                for param in self {
+                    let _ = writeln!(
+                        text,
+                        "{}\t{}\t{}\t{}\t{}",
+                        self.system_id(),
+                        self.component_id(),
+                        param.id,
+                        param.value,
+                        param.value.param_type() as u32
+                    );
+                }
+            }
+        }
+        text
+    }
+}
+
+impl Vehicle {
+    /// Writes every value in `file` that differs from the vehicle, each
+    /// verified as in [`Vehicle::write_param`], and returns how many were
+    /// written.
+    ///
+    /// Failures do not stop the rest of the file; they are collected into
+    /// [`Error::ParamsNotApplied`].
+    pub fn apply_params(&self, file: &ParamFile) -> Result<usize> {
+        self.apply_params_with_table(&self.list_params()?, file)
+    }
+
+    /// Like [`Vehicle::apply_params`], but compares against `table`, a
+    /// recent download, instead of fetching the parameters again.
+    pub fn apply_params_with_table(&self, table: &ParamTable, file: &ParamFile) -> Result<usize> {
+        let mut written = 0;
+        let mut failures = Vec::new();
+        for (id, value) in file.iter() {
+            match self.apply_param(table, id, value) {
+                Ok(true) => written += 1,
+                Ok(false) => {}
+                Err(e @ (Error::Send(_) | Error::Disconnected)) => return Err(e),
+                Err(e) => failures.push((id.clone(), e)),
+            }
+        }
+        if failures.is_empty() {
+            Ok(written)
+        } else {
+            Err(Error::ParamsNotApplied(failures))
+        }
+    }
+
+    /// Returns whether a write was needed.
+    fn apply_param(&self, table: &ParamTable, id: &ParamId, value: f64) -> Result<bool> {
+        let current = table
+            .get(id)
+            .ok_or_else(|| Error::InvalidParam(format!("{id} does not exist on the vehicle")))?;
+        let param_type = current.value.param_type();
+        let value = ParamValue::with_type(param_type, value).ok_or_else(|| {
+            Error::InvalidParam(format!("{id} cannot hold {value} as {param_type:?}"))
+        })?;
+        if value == current.value {
+            return Ok(false);
+        }
+        self.write_param(id, value)?;
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn id(name: &str) -> ParamId {
+        ParamId::new(name).unwrap()
+    }
+
+    fn line_of(result: Result<ParamFile>) -> usize {
+        match result {
+            Err(Error::InvalidParamFile { line, .. }) => line,
+            other => panic!("parsed as {other:?}"),
+        }
+    }
+
+    #[test]
+    fn parses_both_formats() {
+        let parm = ParamFile::parse(
+            "# comment\nWPNAV_SPEED 500\n\nRTL_ALT,1500.5\nWPNAV_SPEED  600\n",
+            ParamFileFormat::Parm,
+        )
+        .unwrap();
+        assert_eq!(parm.len(), 2);
+        assert_eq!(parm.get(&id("WPNAV_SPEED")), Some(600.0));
+        assert_eq!(parm.get(&id("RTL_ALT")), Some(1500.5));
+        let qgc = ParamFile::parse(
+            "# Onboard parameters\n1\t1\tWPNAV_SPEED\t500\t9\n1\t1\tRTL_ALT\t1500.5\t9\n",
+            ParamFileFormat::Qgc,
+        )
+        .unwrap();
+        let names: Vec<_> = qgc.iter().map(|(id, _)| id.as_str()).collect();
+        assert_eq!(names, ["WPNAV_SPEED", "RTL_ALT"]);
+    }
+
+    #[test]
+    fn reports_the_failing_line() {
+        assert_eq!(
+            line_of(ParamFile::parse("A 1\nB", ParamFileFormat::Parm)),
+            2
+        );
+        assert_eq!(
+            line_of(ParamFile::parse("\n\nA x", ParamFileFormat::Parm)),
+            3
+        );
+        assert_eq!(
+            line_of(ParamFile::parse(
+                "A_NAME_LONGER_THAN_16 1",
+                ParamFileFormat::Parm
+            )),
+            1
+        );
+        assert_eq!(
+            line_of(ParamFile::parse("1 1 A 1 9", ParamFileFormat::Qgc)),
+            1
+        );
+    }
+
+    #[test]
+    fn diff_lists_changes_by_name() {
+        let old = ParamFile::parse("C 1\nB 2\nA 3\nD 1000", ParamFileFormat::Parm).unwrap();
+        let new =
+            ParamFile::parse("A 3.00001\nB 2.5\nE 7\nD 1000.001", ParamFileFormat::Parm).unwrap();
+        assert_eq!(
+            old.diff(&new, DIFF_TOLERANCE),
+            [
+                ParamChange::Changed {
+                    id: id("B"),
+                    from: 2.0,
+                    to: 2.5
+                },
+                ParamChange::Removed {
+                    id: id("C"),
+                    value: 1.0
+                },
+                ParamChange::Added {
+                    id: id("E"),
+                    value: 7.0
+                },
+            ]
+        );
+        assert!(old.diff(&old, 0.0).is_empty());
+    }
+}
@@ -0,0 +1,325 @@
+use std::{collections::BTreeMap, fs, path::Path};
+
+use quick_xml::{
+    Reader,
+    escape::unescape,
+    events::{BytesStart, Event},
+};
+
+use crate::{
+    error::{Error, Result},
+    param::ParamId,
+};
+
+/// What ArduPilot documents about one parameter.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ParamMeta {
+    /// Short title, e.g. "Maximum Roll Angle".
+    pub display_name: String,
+    pub description: String,
+    pub units: Option<String>,
+    /// Inclusive minimum and maximum.
+    pub range: Option<(f64, f64)>,
+    /// Named values of an enum parameter.
+    pub values: Vec<(f64, String)>,
+    /// Bit numbers and meanings of a bitmask parameter.
+    pub bitmask: Vec<(u32, String)>,
+    pub read_only: bool,
+}
+
+impl ParamMeta {
+    pub fn in_range(&self, value: f64) -> bool {
+        self.range
+            .is_none_or(|(min, max)| (min..=max).contains(&value))
+    }
+
+    /// The documented name of `value`: its enum label, or the labels of
+    /// its set bits joined with `|`.
+    pub fn describe_value(&self, value: f64) -> Option<String> {
+        if let Some((_, label)) = self.values.iter().find(|(code, _)| *code == value) {
+            return Some(label.clone());
+        }
+        if self.bitmask.is_empty() || value < 0.0 || value.fract() != 0.0 {
+            return None;
+        }
+        let bits = value as u64;
+        let labels: Vec<&str> = self
+            .bitmask
+            .iter()
+            .filter(|(bit, _)| *bit < 64 && bits & (1 << bit) != 0)
+            .map(|(_, label)| label.as_str())
+            .collect();
+        (!labels.is_empty()).then(|| labels.join("|"))
+    }
+}
+
+/// Parameter documentation loaded from ArduPilot's `apm.pdef.xml`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ParamMetaDb {
+    params: BTreeMap<ParamId, ParamMeta>,
+}
+
+impl ParamMetaDb {
+    /// Loads the library parameters plus those of `vehicle`, which names a
+    /// vehicle section of the file such as "ArduCopter", "ArduPlane",
+    /// "Rover" or "ArduSub".
+    pub fn load(path: impl AsRef<Path>, vehicle: &str) -> Result<Self> {
+        let xml = fs::read_to_string(path).map_err(Error::Io)?;
+        Self::parse(&xml, vehicle)
+    }
+
+    pub fn parse(xml: &str, vehicle: &str) -> Result<Self> {
+        let mut reader = Reader::from_str(xml);
+        let mut db = Self::default();
+        let mut current: Option<(ParamId, ParamMeta)> = None;
+        loop {
+            let event = reader.read_event().map_err(|e| invalid(&reader, e))?;
+            match event {
+                Event::Start(e) if e.name().as_ref() == b"param" => {
+                    current = parse_param(&reader, &e, vehicle)?;
+                }
+                Event::Empty(e) if e.name().as_ref() == b"param" => {
+                    if let Some((id, meta)) = parse_param(&reader, &e, vehicle)? {
+                        db.params.insert(id, meta);
+                    }
+                }
+                Event::End(e) if e.name().as_ref() == b"param" => {
+                    if let Some((id, meta)) = current.take() {
+                        db.params.insert(id, meta);
+                    }
+                }
+                Event::Start(e) if e.name().as_ref() == b"field" => {
+                    let name = attribute(&reader, &e, "name")?.unwrap_or_default();
+                    let text = read_text(&mut reader, &e)?;
+                    if let Some((_, meta)) = current.as_mut() {
+                        apply_field(meta, &name, text.trim());
+                    }
+                }
+                Event::Start(e) if e.name().as_ref() == b"value" => {
+                    let code = attribute(&reader, &e, "code")?;
+                    let text = read_text(&mut reader, &e)?;
+                    if let (Some((_, meta)), Some(code)) = (
+                        current.as_mut(),
+                        code.and_then(|code| code.trim().parse().ok()),
+                    ) {
+                        meta.values.push((code, text.trim().to_string()));
+                    }
+                }
+                Event::Eof => return Ok(db),
+                _ => {}
+            }
+        }
+    }
+
+    pub fn get(&self, id: &ParamId) -> Option<&ParamMeta> {
+        self.params.get(id)
+    }
+
+    pub fn len(&self) -> usize {
+        self.params.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.params.is_empty()
+    }
+}
+
+/// Vehicle parameters are named `<vehicle>:<NAME>`; library ones have no
+/// prefix. Returns `None` for another vehicle's parameters.
+fn parse_param(
+    reader: &Reader<&[u8]>,
+    element: &BytesStart,
+    vehicle: &str,
+) -> Result<Option<(ParamId, ParamMeta)>> {
+    let Some(name) = attribute(reader, element, "name")? else {
+        return Ok(None);
+    };
+    let name = match name.split_once(':') {
+        Some((prefix, name)) if prefix == vehicle => name,
+        Some(_) => return Ok(None),
+        None => name.as_str(),
+    };
+    // The file also documents a few names that do not fit in PARAM_VALUE.
+    let Ok(id) = ParamId::new(name) else {
+        return Ok(None);
+    };
+    let meta = ParamMeta {
+        display_name: attribute(reader, element, "humanName")?.unwrap_or_default(),
+        description: attribute(reader, element, "documentation")?.unwrap_or_default(),
+        ..ParamMeta::default()
+    };
+    Ok(Some((id, meta)))
+}
+
+fn apply_field(meta: &mut ParamMeta, name: &str, text: &str) {
+    match name {
+        "Units" => meta.units = Some(text.to_string()),
+        "Range" => {
+            let mut bounds = text.split_whitespace().map(str::parse);
+            if let (Some(Ok(min)), Some(Ok(max))) = (bounds.next(), bounds.next()) {
+                meta.range = Some((min, max));
+            }
+        }
+        "Bitmask" => {
+            meta.bitmask = text
+                .split(',')
+                .filter_map(|entry| {
+                    let (bit, label) = entry.split_once(':')?;
+                    Some((bit.trim().parse().ok()?, label.trim().to_string()))
+                })
+                .collect();
+        }
+        "ReadOnly" => meta.read_only = text.eq_ignore_ascii_case("true"),
+        _ => {}
+    }
+}
+
+fn attribute(reader: &Reader<&[u8]>, element: &BytesStart, name: &str) -> Result<Option<String>> {
+    let attribute = element
+        .try_get_attribute(name)
+        .map_err(|e| invalid(reader, e))?;
+    attribute
+        .map(|attribute| {
+            attribute
+                .unescape_value()
+                .map(|value| value.into_owned())
+                .map_err(|e| invalid(reader, e))
+        })
+        .transpose()
+}
+
+fn read_text(reader: &mut Reader<&[u8]>, element: &BytesStart) -> Result<String> {
+    let raw = reader
+        .read_text(element.name())
+        .map_err(|e| invalid(reader, e))?;
+    unescape(&raw)
+        .map(|text| text.into_owned())
+        .map_err(|e| invalid(reader, e))
+}
+
+fn invalid(reader: &Reader<&[u8]>, error: impl std::fmt::Display) -> Error {
+    Error::InvalidParamMetadata(format!("{error} at byte {}", reader.buffer_position()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock;
+
+    const PDEF: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+<paramfile>
+  <vehicles>
+    <parameters name="ArduCopter">
+      <param humanName="Angle Max" name="ArduCopter:ANGLE_MAX" documentation="Maximum lean angle in all flight modes" user="Advanced">
+        <field name="Units">cdeg</field>
+        <field name="Range">1000 8000</field>
+      </param>
+      <param humanName="Eeprom format version number" name="ArduCopter:FORMAT_VERSION" documentation="">
+        <field name="ReadOnly">True</field>
+      </param>
+    </parameters>
+    <parameters name="ArduPlane">
+      <param humanName="Throttle cruise percentage" name="ArduPlane:TRIM_THROTTLE" documentation="">
+        <field name="Range">0 100</field>
+      </param>
+    </parameters>
+  </vehicles>
+  <libraries>
+    <parameters name="BATT_">
+      <param humanName="Battery monitoring" name="BATT_MONITOR" documentation="Voltage &amp; current &lt;sensing&gt;">
+        <values>
+          <value code="0">Disabled</value>
+          <value code="4">Analog Voltage &amp; Current</value>
+        </values>
+      </param>
+    </parameters>
+    <parameters name="LOG">
+      <param humanName="Log bitmask" name="LOG_BITMASK" documentation="">
+        <field name="Bitmask">0:Fast Attitude, 1:Medium Attitude,2: GPS</field>
+      </param>
+      <param humanName="Too long" name="A_NAME_LONGER_THAN_16" documentation=""/>
+      <param humanName="Empty" name="LOG_DISARMED" documentation="Log while disarmed"/>
+    </parameters>
+  </libraries>
+</paramfile>
+"#;
+
+    fn id(name: &str) -> ParamId {
+        ParamId::new(name).unwrap()
+    }
+
+    #[test]
+    fn keeps_one_vehicle_and_the_libraries() {
+        let db = ParamMetaDb::parse(PDEF, "ArduCopter").unwrap();
+        assert_eq!(db.len(), 5);
+        assert!(db.get(&id("TRIM_THROTTLE")).is_none());
+        let angle_max = db.get(&id("ANGLE_MAX")).unwrap();
+        assert_eq!(angle_max.display_name, "Angle Max");
+        assert_eq!(angle_max.units.as_deref(), Some("cdeg"));
+        assert_eq!(angle_max.range, Some((1000.0, 8000.0)));
+        assert!(db.get(&id("FORMAT_VERSION")).unwrap().read_only);
+        assert_eq!(
+            db.get(&id("LOG_DISARMED")).unwrap().description,
+            "Log while disarmed"
+        );
+
+        let plane = ParamMetaDb::parse(PDEF, "ArduPlane").unwrap();
+        assert!(plane.get(&id("ANGLE_MAX")).is_none());
+        assert_eq!(
+            plane.get(&id("TRIM_THROTTLE")).unwrap().range,
+            Some((0.0, 100.0))
+        );
+    }
+
+    #[test]
+    fn reads_values_and_bitmasks() {
+        let db = ParamMetaDb::parse(PDEF, "ArduCopter").unwrap();
+        let monitor = db.get(&id("BATT_MONITOR")).unwrap();
+        assert_eq!(monitor.description, "Voltage & current <sensing>");
+        assert_eq!(
+            monitor.values,
+            [
+                (0.0, "Disabled".to_string()),
+                (4.0, "Analog Voltage & Current".to_string())
+            ]
+        );
+        assert_eq!(
+            monitor.describe_value(4.0).as_deref(),
+            Some("Analog Voltage & Current")
+        );
+        let log = db.get(&id("LOG_BITMASK")).unwrap();
+        assert_eq!(log.bitmask.len(), 3);
+        assert_eq!(
+            log.describe_value(5.0).as_deref(),
+            Some("Fast Attitude|GPS")
+        );
+        assert_eq!(log.describe_value(0.5), None);
+    }
+
+    #[test]
+    fn reports_malformed_xml() {
+        assert!(matches!(
+            ParamMetaDb::parse("<paramfile><param name=\"A\"></paramfile>", "ArduCopter"),
+            Err(Error::InvalidParamMetadata(_))
+        ));
+    }
+
+    #[test]
+    fn set_param_refuses_values_outside_the_documented_range() {
+        let (vehicle, peer) = mock::vehicle(|_| Vec::new());
+        let vehicle = vehicle.with_param_metadata(ParamMetaDb::parse(PDEF, "ArduCopter").unwrap());
+        match vehicle.set_param(&id("ANGLE_MAX"), 9000.0) {
+            Err(Error::OutOfRange {
+                id: refused,
+                value,
+                min,
+                max,
+            }) => assert_eq!(
+                (refused, value, min, max),
+                (id("ANGLE_MAX"), 9000.0, 1000.0, 8000.0)
+            ),
+            other => panic!("set_param returned {other:?}"),
+        }
+        assert!(peer.sent().is_empty());
+    }
+}
@@ -0,0 +1,496 @@
+use std::{fs, path::Path};
+
+use mavlink::ardupilotmega::{MavCmd, MavFrame};
+use num_traits::FromPrimitive;
+use serde_json::{Value, json};
+
+use crate::{
+    error::{Error, Result},
+    fence::{Fence, FenceZone},
+    geo::LatLon,
+    mission::{MissionItem, to_xy, xy_scale},
+    rally::RallyPoint,
+};
+
+/// MAV_AUTOPILOT_ARDUPILOTMEGA, recorded as the firmware a written plan is for.
+const FIRMWARE_ARDUPILOT: u32 = 3;
+
+/// MAV_AUTOPILOT_PX4, whose missions have no home item.
+const FIRMWARE_PX4: u64 = 12;
+
+/// A QGroundControl `.plan` file: a mission, a geofence and rally points in
+/// one JSON document.
+///
+/// Only SimpleItem mission entries are supported; complex items such as
+/// surveys and corridor scans are rejected with [`Error::InvalidPlan`].
+///
+/// The mission is laid out for ArduPilot, which keeps home as item 0. PX4
+/// missions start with their first real item, so plans written for PX4 are
+/// refused by [`Plan::parse`], and a PX4 mission must have a home item put in
+/// front of it before [`Plan::to_json`].
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Plan {
+    /// The mission as sent to ArduPilot: item 0 is the planned home position.
+    pub mission: Vec<MissionItem>,
+    pub fence: Fence,
+    pub rally_points: Vec<RallyPoint>,
+}
+
+impl Plan {
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        Self::parse(&fs::read_to_string(path).map_err(Error::Io)?)
+    }
+
+    pub fn parse(text: &str) -> Result<Self> {
+        let root: Value = serde_json::from_str(text).map_err(|e| invalid(e.to_string()))?;
+        if root["fileType"] != "Plan" {
+            return Err(invalid("fileType is not \"Plan\""));
+        }
+        if root["mission"]["firmwareType"].as_u64() == Some(FIRMWARE_PX4) {
+            return Err(invalid(
+                "PX4 plans are not supported: their missions have no home item",
+            ));
+        }
+        Ok(Self {
+            mission: parse_mission(&root["mission"])?,
+            fence: parse_fence(&root["geoFence"])?,
+            rally_points: parse_rally_points(&root["rallyPoints"])?,
+        })
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        fs::write(path, self.to_json()).map_err(Error::Io)
+    }
+
+    /// The file contents, pretty-printed with sorted keys as QGroundControl
+    /// writes them. Mission item 0 becomes the planned home position, as it
+    /// is on ArduPilot; an empty mission is written with home at [0, 0, 0].
+    pub fn to_json(&self) -> String {
+        let (home, items) = match self.mission.split_first() {
+            Some((home, items)) => (
+                json!([
+                    home.x as f64 / xy_scale(home.frame),
+                    home.y as f64 / xy_scale(home.frame),
+                    float(home.z)
+                ]),
+                items,
+            ),
+            None => (json!([0, 0, 0]), &[][..]),
+        };
+        // doJumpId numbers items as the vehicle does, so DO_JUMP targets
+        // carry over unchanged.
+        let items: Vec<Value> = items
+            .iter()
+            .zip(1..)
+            .map(|(item, seq)| {
+                let scale = xy_scale(item.frame);
+                let [param1, param2, param3, param4] = item.params.map(float);
+                json!({
+                    "type": "SimpleItem",
+                    "autoContinue": item.autocontinue,
+                    "command": item.command as u32,
+                    "doJumpId": seq,
+                    "frame": item.frame as u32,
+                    "params": [
+                        param1,
+                        param2,
+                        param3,
+                        param4,
+                        item.x as f64 / scale,
+                        item.y as f64 / scale,
+                        float(item.z)
+                    ],
+                })
+            })
+            .collect();
+        let mut polygons = Vec::new();
+        let mut circles = Vec::new();
+        for zone in &self.fence.zones {
+            match zone {
+                FenceZone::InclusionPolygon(vertices) | FenceZone::ExclusionPolygon(vertices) => {
+                    let vertices: Vec<Value> = vertices
+                        .iter()
+                        .map(|vertex| json!([vertex.latitude, vertex.longitude]))
+                        .collect();
+                    polygons.push(json!({
+                        "inclusion": matches!(zone, FenceZone::InclusionPolygon(_)),
+                        "polygon": vertices,
+                        "version": 1,
+                    }))
+                }
+                FenceZone::InclusionCircle { center, radius }
+                | FenceZone::ExclusionCircle { center, radius } => circles.push(json!({
+                    "circle": {
+                        "center": [center.latitude, center.longitude],
+                        "radius": float(*radius),
+                    },
+                    "inclusion": matches!(zone, FenceZone::InclusionCircle { .. }),
+                    "version": 1,
+                })),
+            }
+        }
+        let mut fence = json!({ "circles": circles, "polygons": polygons, "version": 2 });
+        if let Some((position, altitude)) = self.fence.return_point {
+            fence["breachReturn"] = json!([position.latitude, position.longitude, float(altitude)]);
+        }
+        let rally_points: Vec<Value> = self
+            .rally_points
+            .iter()
+            .map(|point| {
+                json!([
+                    point.position.latitude,
+                    point.position.longitude,
+                    float(point.altitude)
+                ])
+            })
+            .collect();
+        let root = json!({
+            "fileType": "Plan",
+            "geoFence": fence,
+            "groundStation": env!("CARGO_PKG_NAME"),
+            "mission": {
+                "firmwareType": FIRMWARE_ARDUPILOT,
+                "items": items,
+                "plannedHomePosition": home,
+                "version": 2,
+            },
+            "rallyPoints": { "points": rally_points, "version": 2 },
+            "version": 1,
+        });
+        format!("{root:#}\n")
+    }
+}
+
+fn parse_mission(mission: &Value) -> Result<Vec<MissionItem>> {
+    let items = optional_array(&mission["items"], "mission items")?;
+    if items.is_empty() && mission["plannedHomePosition"].is_null() {
+        return Ok(Vec::new());
+    }
+    let home = match numbers(&mission["plannedHomePosition"], "plannedHomePosition")?[..] {
+        // What `to_json` writes for an empty mission, rather than a real home.
+        [0.0, 0.0, 0.0] if items.is_empty() => return Ok(Vec::new()),
+        [latitude, longitude, altitude] => {
+            MissionItem::waypoint(latitude, longitude, altitude as f32)
+                .map_err(|e| invalid(format!("plannedHomePosition: {e}")))?
+                .frame(MavFrame::MAV_FRAME_GLOBAL)
+        }
+        _ => return Err(invalid("plannedHomePosition is not [lat, lon, alt]")),
+    };
+    let mut mission = vec![home];
+    let mut jump_ids = Vec::new();
+    for (n, item) in items.iter().enumerate() {
+        mission.push(parse_item(n, item)?);
+        if let Some(id) = item["doJumpId"].as_f64() {
+            jump_ids.push((id as f32, n + 1));
+        }
+    }
+    // DO_JUMP param1 names the target's doJumpId, not its position.
+    for (n, item) in mission.iter_mut().enumerate().skip(1) {
+        if item.command != MavCmd::MAV_CMD_DO_JUMP {
+            continue;
+        }
+        let target = item.params[0];
+        let Some(&(_, seq)) = jump_ids.iter().find(|(id, _)| *id == target) else {
+            return Err(invalid(format!(
+                "mission item {} jumps to doJumpId {target}, which no item has",
+                n - 1
+            )));
+        };
+        item.params[0] = seq as f32;
+    }
+    Ok(mission)
+}
+
+fn parse_item(n: usize, item: &Value) -> Result<MissionItem> {
+    let invalid_item = |message: String| invalid(format!("mission item {n}: {message}"));
+    match item["type"].as_str() {
+        Some("SimpleItem") => {}
+        Some("ComplexItem") => {
+            return Err(invalid_item(format!(
+                "complex item {} is not supported",
+                item["complexItemType"]
+                    .as_str()
+                    .unwrap_or("of unknown type")
+            )));
+        }
+        _ => return Err(invalid_item(format!("unknown type {}", item["type"]))),
+    }
+    let command = item["command"]
+        .as_u64()
+        .and_then(MavCmd::from_u64)
+        .ok_or_else(|| invalid_item(format!("unknown command {}", item["command"])))?;
+    let frame = item["frame"]
+        .as_u64()
+        .and_then(MavFrame::from_u64)
+        .ok_or_else(|| invalid_item(format!("unknown frame {}", item["frame"])))?;
+    // QGroundControl writes NaN params as null.
+    let params = item["params"]
+        .as_array()
+        .ok_or_else(|| invalid_item("params is not a list".to_string()))?
+        .iter()
+        .map(|param| match param {
+            Value::Null => Ok(f64::NAN),
+            param => param
+                .as_f64()
+                .ok_or_else(|| invalid_item(format!("param {param} is not a number"))),
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let [param1, param2, param3, param4, x, y, z] = params[..] else {
+        return Err(invalid_item(format!(
+            "expected 7 params, not {}",
+            params.len()
+        )));
+    };
+    let scaled = |name: &str, value: f64| {
+        to_xy(value, frame).ok_or_else(|| invalid_item(format!("{name} {value} is out of range")))
+    };
+    Ok(MissionItem {
+        command,
+        frame,
+        params: [param1, param2, param3, param4].map(|param| param as f32),
+        x: scaled("x", x)?,
+        y: scaled("y", y)?,
+        z: z as f32,
+        current: false,
+        autocontinue: item["autoContinue"].as_bool().unwrap_or(true),
+    })
+}
+
+fn parse_fence(fence: &Value) -> Result<Fence> {
+    let mut result = Fence::new();
+    for (n, polygon) in optional_array(&fence["polygons"], "fence polygons")?
+        .iter()
+        .enumerate()
+    {
+        let vertices = array(&polygon["polygon"], "fence polygon")?
+            .iter()
+            .map(|vertex| lat_lon(vertex, "fence vertex"))
+            .collect::<Result<Vec<_>>>()?;
+        if vertices.len() < 3 {
+            return Err(invalid(format!(
+                "fence polygon {n} has {} vertices, needs at least 3",
+                vertices.len()
+            )));
+        }
+        result.zones.push(if inclusion(polygon) {
+            FenceZone::InclusionPolygon(vertices)
+        } else {
+            FenceZone::ExclusionPolygon(vertices)
+        });
+    }
+    for circle in optional_array(&fence["circles"], "fence circles")? {
+        let center = lat_lon(&circle["circle"]["center"], "fence circle center")?;
+        let radius = circle["circle"]["radius"]
+            .as_f64()
+            .ok_or_else(|| invalid("fence circle radius is not a number"))?
+            as f32;
+        result.zones.push(if inclusion(circle) {
+            FenceZone::InclusionCircle { center, radius }
+        } else {
+            FenceZone::ExclusionCircle { center, radius }
+        });
+    }
+    if !fence["breachReturn"].is_null() {
+        match numbers(&fence["breachReturn"], "breachReturn")?[..] {
+            [latitude, longitude, altitude] => {
+                result.return_point = Some((LatLon::new(latitude, longitude), altitude as f32));
+            }
+            _ => return Err(invalid("breachReturn is not [lat, lon, alt]")),
+        }
+    }
+    Ok(result)
+}
+
+fn parse_rally_points(rally_points: &Value) -> Result<Vec<RallyPoint>> {
+    optional_array(&rally_points["points"], "rally points")?
+        .iter()
+        .map(|point| match numbers(point, "rally point")?[..] {
+            [latitude, longitude, altitude] => {
+                Ok(RallyPoint::new(latitude, longitude, altitude as f32))
+            }
+            _ => Err(invalid("rally point is not [lat, lon, alt]")),
+        })
+        .collect()
+}
+
+/// Fence zones without an `inclusion` key are inclusion zones.
+fn inclusion(zone: &Value) -> bool {
+    zone["inclusion"].as_bool().unwrap_or(true)
+}
+
+fn lat_lon(value: &Value, what: &str) -> Result<LatLon> {
+    match numbers(value, what)?[..] {
+        [latitude, longitude] => Ok(LatLon::new(latitude, longitude)),
+        _ => Err(invalid(format!("{what} is not [lat, lon]"))),
+    }
+}
+
+fn numbers(value: &Value, what: &str) -> Result<Vec<f64>> {
+    array(value, what)?
+        .iter()
+        .map(|number| {
+            number
+                .as_f64()
+                .ok_or_else(|| invalid(format!("{what} holds {number}, which is not a number")))
+        })
+        .collect()
+}
+
+fn array<'a>(value: &'a Value, what: &str) -> Result<&'a [Value]> {
+    value
+        .as_array()
+        .map(Vec::as_slice)
+        .ok_or_else(|| invalid(format!("{what} is not a list")))
+}
+
+/// Like [`array`], but a missing section counts as empty.
+fn optional_array<'a>(value: &'a Value, what: &str) -> Result<&'a [Value]> {
+    if value.is_null() {
+        Ok(&[])
+    } else {
+        array(value, what)
+    }
+}
+
+/// `value` with the digits it was given as f32 rather than its exact f64
+/// expansion; NaN becomes null.
+fn float(value: f32) -> Value {
+    value
+        .to_string()
+        .parse::<f64>()
+        .map_or(Value::Null, Value::from)
+}
+
+fn invalid(message: impl Into<String>) -> Error {
+    Error::InvalidPlan(message.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    const PLAN: &str = r#"{
+        "fileType": "Plan",
+        "geoFence": {
+            "circles": [
+                { "circle": { "center": [-35.36, 149.16], "radius": 30.5 }, "inclusion": false, "version": 1 }
+            ],
+            "polygons": [
+                { "inclusion": true, "polygon": [[-35.1, 149.1], [-35.2, 149.1], [-35.2, 149.2]], "version": 1 }
+            ],
+            "breachReturn": [-35.3, 149.3, 40],
+            "version": 2
+        },
+        "groundStation": "QGroundControl",
+        "mission": {
+            "firmwareType": 3,
+            "items": [
+                { "autoContinue": true, "command": 22, "doJumpId": 1, "frame": 3, "params": [0, 0, 0, null, -35.3632621, 149.1652374, 50], "type": "SimpleItem" },
+                { "autoContinue": true, "command": 16, "doJumpId": 5, "frame": 3, "params": [5, 2, 0, null, -35.36125769, 149.16517199, 100], "type": "SimpleItem" },
+                { "autoContinue": true, "command": 177, "doJumpId": 6, "frame": 2, "params": [5, 3, 0, 0, 0, 0, 0], "type": "SimpleItem" }
+            ],
+            "plannedHomePosition": [-35.3632621, 149.1652374, 584.09],
+            "version": 2
+        },
+        "rallyPoints": { "points": [[-35.4, 149.4, 30], [-35.5, 149.5, 31]], "version": 2 },
+        "version": 1
+    }"#;
+
+    fn error(text: &str) -> String {
+        match Plan::parse(text) {
+            Err(e @ Error::InvalidPlan(_)) => e.to_string(),
+            other => panic!("parsed as {other:?}"),
+        }
+    }
+
+    #[test]
+    fn parses_qgc_plans() {
+        let plan = Plan::parse(PLAN).unwrap();
+        assert_eq!(plan.mission.len(), 4);
+        assert_eq!(plan.mission[0].frame, MavFrame::MAV_FRAME_GLOBAL);
+        assert_eq!(plan.mission[0].z, 584.09);
+        assert!(plan.mission[1].params[3].is_nan());
+        assert_eq!(plan.mission[2].x, -353612577);
+        // doJumpId 5 is the waypoint at position 2.
+        assert_eq!(plan.mission[3].params[0], 2.0);
+        assert_eq!(plan.fence.zones.len(), 2);
+        assert_eq!(
+            plan.fence.return_point.map(|(_, altitude)| altitude),
+            Some(40.0)
+        );
+        assert_eq!(plan.rally_points[1], RallyPoint::new(-35.5, 149.5, 31.0));
+    }
+
+    #[test]
+    fn round_trips_through_json() {
+        let plan = Plan {
+            mission: vec![
+                MissionItem::waypoint(-35.3632621, 149.1652374, 584.09)
+                    .unwrap()
+                    .frame(MavFrame::MAV_FRAME_GLOBAL),
+                MissionItem::takeoff(50.0),
+                MissionItem::waypoint(-35.36125769, 149.16517199, 100.0)
+                    .unwrap()
+                    .hold(Duration::from_secs(5))
+                    .accept_radius(0.1),
+                MissionItem::do_jump(2, 3),
+            ],
+            fence: Fence::new()
+                .return_point(-35.3, 149.3, 40.0)
+                .exclusion_polygon([(-35.1, 149.1), (-35.2, 149.1), (-35.2, 149.2)])
+                .inclusion_circle(-35.36, 149.16, 30.5),
+            rally_points: vec![RallyPoint::new(-35.4, 149.4, 30.0)],
+        };
+        assert_eq!(Plan::parse(&plan.to_json()).unwrap(), plan);
+        assert_eq!(
+            Plan::parse(&Plan::default().to_json()).unwrap(),
+            Plan::default()
+        );
+    }
+
+    #[test]
+    fn keeps_home_without_other_items() {
+        let plan = Plan {
+            mission: vec![
+                MissionItem::waypoint(-35.3632621, 149.1652374, 584.09)
+                    .unwrap()
+                    .frame(MavFrame::MAV_FRAME_GLOBAL),
+            ],
+            ..Plan::default()
+        };
+        assert_eq!(Plan::parse(&plan.to_json()).unwrap(), plan);
+    }
+
+    #[test]
+    fn rejects_px4_plans() {
+        assert_eq!(
+            error(&PLAN.replace(r#""firmwareType": 3"#, r#""firmwareType": 12"#)),
+            "invalid plan file: PX4 plans are not supported: their missions have no home item"
+        );
+    }
+
+    #[test]
+    fn rejects_complex_items() {
+        let survey = PLAN.replace(
+            r#"{ "autoContinue": true, "command": 177, "doJumpId": 6, "frame": 2, "params": [5, 3, 0, 0, 0, 0, 0], "type": "SimpleItem" }"#,
+            r#"{ "type": "ComplexItem", "complexItemType": "survey", "version": 5 }"#,
+        );
+        assert_eq!(
+            error(&survey),
+            "invalid plan file: mission item 2: complex item survey is not supported"
+        );
+    }
+
+    #[test]
+    fn rejects_malformed_plans() {
+        error("{");
+        error(r#"{ "fileType": "Other" }"#);
+        error(&PLAN.replace(r#""doJumpId": 5"#, r#""doJumpId": 7"#));
+        error(&PLAN.replace("[-35.4, 149.4, 30]", "[1]"));
+        error(&PLAN.replace("[[-35.1, 149.1], ", "["));
+        error(&PLAN.replace("-35.36125769, 149.16517199", "1e10, 149.16517199"));
+        error(&PLAN.replace("-35.36125769, 149.16517199", "null, 149.16517199"));
+    }
+}
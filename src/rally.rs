@@ -0,0 +1,90 @@
+use mavlink::ardupilotmega::{MavCmd, MavFrame, MavMissionType};
+
+use crate::{
+    error::{Error, Result},
+    geo::LatLon,
+    mission::MissionItem,
+    vehicle::Vehicle,
+};
+
+/// A place the vehicle may return to instead of home, stored as a
+/// MAV_MISSION_TYPE_RALLY item.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RallyPoint {
+    pub position: LatLon,
+    /// Metres above home.
+    pub altitude: f32,
+}
+
+impl RallyPoint {
+    pub fn new(latitude: f64, longitude: f64, altitude: f32) -> Self {
+        Self {
+            position: LatLon::new(latitude, longitude),
+            altitude,
+        }
+    }
+
+    pub fn to_item(&self) -> Result<MissionItem> {
+        Ok(MissionItem::new(
+            MavCmd::MAV_CMD_NAV_RALLY_POINT,
+            MavFrame::MAV_FRAME_GLOBAL_RELATIVE_ALT,
+        )
+        .location(self.position.latitude, self.position.longitude)?
+        .altitude(self.altitude))
+    }
+
+    pub fn from_item(item: &MissionItem) -> Result<Self> {
+        if item.command != MavCmd::MAV_CMD_NAV_RALLY_POINT {
+            return Err(Error::InvalidMission(format!(
+                "{:?} is not a rally point",
+                item.command
+            )));
+        }
+        Ok(Self {
+            position: LatLon::from_e7(item.x, item.y),
+            altitude: item.z,
+        })
+    }
+}
+
+impl Vehicle {
+    /// Replaces the vehicle's rally points.
+    pub fn upload_rally_points(&self, points: &[RallyPoint]) -> Result<()> {
+        let items = points
+            .iter()
+            .map(RallyPoint::to_item)
+            .collect::<Result<Vec<_>>>()?;
+        self.upload_items(MavMissionType::MAV_MISSION_TYPE_RALLY, &items)
+    }
+
+    pub fn download_rally_points(&self) -> Result<Vec<RallyPoint>> {
+        self.download_items(MavMissionType::MAV_MISSION_TYPE_RALLY)?
+            .iter()
+            .map(RallyPoint::from_item)
+            .collect()
+    }
+
+    pub fn clear_rally_points(&self) -> Result<()> {
+        self.clear_items(MavMissionType::MAV_MISSION_TYPE_RALLY)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips_through_items() {
+        let point = RallyPoint::new(-35.3632621, 149.1652374, 40.0);
+        assert_eq!(
+            RallyPoint::from_item(&point.to_item().unwrap()).unwrap(),
+            point
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_positions() {
+        let point = RallyPoint::new(-35.36, 1491.6, 40.0);
+        assert!(matches!(point.to_item(), Err(Error::InvalidPosition(_))));
+    }
+}
@@ -0,0 +1,272 @@
+use std::{fmt::Write as _, fs, path::Path};
+
+use mavlink::ardupilotmega::{MavCmd, MavFrame};
+
+use crate::{
+    error::{Error, Result},
+    geo::LatLon,
+    mission::MissionItem,
+};
+
+/// Opening of every GPX document written here.
+pub(crate) const GPX_HEADER: &str = concat!(
+    "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n",
+    "<gpx version=\"1.1\" creator=\"",
+    env!("CARGO_PKG_NAME"),
+    "\" xmlns=\"http://www.topografix.com/GPX/1/1\">\n"
+);
+
+/// A route point's height, in metres, and what it is measured from.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Altitude {
+    AboveSeaLevel(f32),
+    /// Above the home position, whose own altitude is unknown.
+    AboveHome(f32),
+    AboveTerrain(f32),
+}
+
+impl Altitude {
+    fn metres(self) -> f32 {
+        match self {
+            Self::AboveSeaLevel(metres) | Self::AboveHome(metres) | Self::AboveTerrain(metres) => {
+                metres
+            }
+        }
+    }
+
+    /// The KML altitudeMode for this reference. KML has no mode for heights
+    /// above home, so those are drawn on the ground.
+    fn kml_mode(self) -> &'static str {
+        match self {
+            Self::AboveSeaLevel(_) => "absolute",
+            Self::AboveHome(_) => "clampToGround",
+            Self::AboveTerrain(_) => "relativeToGround",
+        }
+    }
+}
+
+/// One place a mission flies to.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RoutePoint {
+    /// The item's position in the mission.
+    pub seq: u16,
+    pub command: MavCmd,
+    pub position: LatLon,
+    pub altitude: Altitude,
+}
+
+impl RoutePoint {
+    /// `2 WAYPOINT`, for the point's label in a map.
+    fn name(&self) -> String {
+        let command = format!("{:?}", self.command);
+        format!(
+            "{} {}",
+            self.seq,
+            command.trim_start_matches("MAV_CMD_NAV_")
+        )
+    }
+}
+
+/// The path of a mission, for viewing in Google Earth or GIS tools.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Route {
+    pub points: Vec<RoutePoint>,
+}
+
+impl Route {
+    /// The navigation items of `items` that have a position. Items at 0, 0,
+    /// such as a takeoff or landing in place, are left out.
+    ///
+    /// When item 0 is ArduPilot's home in MAV_FRAME_GLOBAL, heights above
+    /// home are raised by its altitude to heights above mean sea level.
+    /// Heights above terrain are kept as they are.
+    pub fn from_mission(items: &[MissionItem]) -> Self {
+        let home_altitude = items
+            .first()
+            .filter(|home| is_sea_level(home.frame))
+            .map(|home| home.z);
+        let points = items
+            .iter()
+            .zip(0..)
+            .filter(|(item, _)| (item.command as u32) < MavCmd::MAV_CMD_NAV_LAST as u32)
+            .filter(|(item, _)| item.x != 0 || item.y != 0)
+            .filter_map(|(item, seq)| {
+                let altitude = match item.frame {
+                    frame if is_sea_level(frame) => Altitude::AboveSeaLevel(item.z),
+                    MavFrame::MAV_FRAME_GLOBAL_TERRAIN_ALT
+                    | MavFrame::MAV_FRAME_GLOBAL_TERRAIN_ALT_INT => Altitude::AboveTerrain(item.z),
+                    _ => match home_altitude {
+                        Some(home) => Altitude::AboveSeaLevel(home + item.z),
+                        None => Altitude::AboveHome(item.z),
+                    },
+                };
+                Some(RoutePoint {
+                    seq,
+                    command: item.command,
+                    position: item.lat_lon()?,
+                    altitude,
+                })
+            })
+            .collect();
+        Self { points }
+    }
+
+    /// Writes the route to `path` as KML if it ends in `.kml`, as GPX otherwise.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        let text = match path.extension() {
+            Some(extension) if extension.eq_ignore_ascii_case("kml") => self.to_kml(),
+            _ => self.to_gpx(),
+        };
+        fs::write(path, text).map_err(Error::Io)
+    }
+
+    /// A GPX route with one `rtept` per point. GPX `ele` is above mean sea
+    /// level, so it is left out for points measured from home or terrain.
+    pub fn to_gpx(&self) -> String {
+        let mut text = String::from(GPX_HEADER);
+        let _ = writeln!(text, "  <rte>\n    <name>Mission</name>");
+        for point in &self.points {
+            let elevation = match point.altitude {
+                Altitude::AboveSeaLevel(metres) => format!("<ele>{metres}</ele>"),
+                _ => String::new(),
+            };
+            let _ = writeln!(
+                text,
+                "    <rtept lat=\"{}\" lon=\"{}\">{elevation}<name>{}</name></rtept>",
+                point.position.latitude,
+                point.position.longitude,
+                point.name()
+            );
+        }
+        let _ = writeln!(text, "  </rte>\n</gpx>");
+        text
+    }
+
+    /// A KML document with the route as a line and a labelled pin per point.
+    /// Each pin has its own altitudeMode; the line is drawn on the ground
+    /// unless every point shares one.
+    pub fn to_kml(&self) -> String {
+        let line_mode = match self.points.split_first() {
+            Some((first, rest))
+                if rest
+                    .iter()
+                    .all(|point| point.altitude.kml_mode() == first.altitude.kml_mode()) =>
+            {
+                first.altitude.kml_mode()
+            }
+            _ => "clampToGround",
+        };
+        let coordinates = |point: &RoutePoint| {
+            format!(
+                "{},{},{}",
+                point.position.longitude,
+                point.position.latitude,
+                point.altitude.metres()
+            )
+        };
+        let mut text = String::new();
+        let _ = writeln!(text, "<?xml version=\"1.0\" encoding=\"UTF-8\"?>");
+        let _ = writeln!(text, "<kml xmlns=\"http://www.opengis.net/kml/2.2\">");
+        let _ = writeln!(text, "  <Document>\n    <name>Mission</name>");
+        let _ = writeln!(
+            text,
+            "    <Placemark>\n      <name>Route</name>\n      <LineString>"
+        );
+        let _ = writeln!(text, "        <altitudeMode>{line_mode}</altitudeMode>");
+        let path: Vec<String> = self.points.iter().map(coordinates).collect();
+        let _ = writeln!(
+            text,
+            "        <coordinates>{}</coordinates>",
+            path.join(" ")
+        );
+        let _ = writeln!(text, "      </LineString>\n    </Placemark>");
+        for point in &self.points {
+            let _ = writeln!(
+                text,
+                "    <Placemark><name>{}</name><Point><altitudeMode>{}</altitudeMode>\
+                 <coordinates>{}</coordinates></Point></Placemark>",
+                point.name(),
+                point.altitude.kml_mode(),
+                coordinates(point)
+            );
+        }
+        let _ = writeln!(text, "  </Document>\n</kml>");
+        text
+    }
+}
+
+/// Whether z is metres above mean sea level in `frame`.
+fn is_sea_level(frame: MavFrame) -> bool {
+    matches!(
+        frame,
+        MavFrame::MAV_FRAME_GLOBAL | MavFrame::MAV_FRAME_GLOBAL_INT
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mission() -> Vec<MissionItem> {
+        vec![
+            MissionItem::waypoint(-35.3632621, 149.1652374, 584.0)
+                .unwrap()
+                .frame(MavFrame::MAV_FRAME_GLOBAL),
+            MissionItem::takeoff(20.0),
+            MissionItem::waypoint(-35.36, 149.16, 50.0).unwrap(),
+            MissionItem::waypoint(-35.37, 149.17, 30.0)
+                .unwrap()
+                .frame(MavFrame::MAV_FRAME_GLOBAL_TERRAIN_ALT_INT),
+            MissionItem::rtl(),
+        ]
+    }
+
+    #[test]
+    fn raises_heights_above_home_to_sea_level() {
+        let route = Route::from_mission(&mission());
+        let altitudes: Vec<_> = route.points.iter().map(|point| point.altitude).collect();
+        assert_eq!(
+            altitudes,
+            [
+                Altitude::AboveSeaLevel(584.0),
+                Altitude::AboveSeaLevel(634.0),
+                Altitude::AboveTerrain(30.0),
+            ]
+        );
+        assert_eq!(route.points[1].seq, 2);
+        assert_eq!(route.points[1].name(), "2 WAYPOINT");
+    }
+
+    #[test]
+    fn keeps_heights_above_home_without_a_home_altitude() {
+        let route = Route::from_mission(&mission()[1..]);
+        assert_eq!(route.points[0].altitude, Altitude::AboveHome(50.0));
+        let gpx = route.to_gpx();
+        assert!(!gpx.contains("<ele>"), "{gpx}");
+        let kml = route.to_kml();
+        assert!(kml.contains("<Point><altitudeMode>clampToGround"), "{kml}");
+        assert!(
+            kml.contains("<Point><altitudeMode>relativeToGround"),
+            "{kml}"
+        );
+        assert!(kml.contains("        <altitudeMode>clampToGround"), "{kml}");
+    }
+
+    #[test]
+    fn writes_sea_level_elevations_only() {
+        let route = Route::from_mission(&mission());
+        let gpx = route.to_gpx();
+        assert_eq!(gpx.matches("<ele>").count(), 2, "{gpx}");
+        assert!(
+            gpx.contains("<ele>634</ele><name>2 WAYPOINT</name>"),
+            "{gpx}"
+        );
+        let kml = Route {
+            points: route.points[..2].to_vec(),
+        }
+        .to_kml();
+        assert!(kml.contains("        <altitudeMode>absolute"), "{kml}");
+        assert!(kml.contains(" 149.16,-35.36,634</coordinates>"), "{kml}");
+    }
+}
@@ -0,0 +1,260 @@
+use std::{
+    sync::{
+        Arc, Mutex, Weak,
+        mpsc::{self, Receiver, RecvTimeoutError, Sender},
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use mavlink::{MavConnection, MavHeader, ardupilotmega::MavMessage, error::MessageReadError};
+
+use crate::error::{Error, Result};
+
+pub type Connection = Box<dyn MavConnection<MavMessage> + Send + Sync>;
+
+/// Senders of the live subscriptions; `None` once the link has closed.
+type Subscribers = Mutex<Option<Vec<Sender<(MavHeader, MavMessage)>>>>;
+
+const IDLE_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// One end of a MAVLink link: owns the connection and a receive thread that
+/// fans every incoming message out to all live [`Subscription`]s.
+#[derive(Clone)]
+pub struct GroundStation {
+    connection: Arc<Connection>,
+    subscribers: Arc<Subscribers>,
+    header: MavHeader,
+}
+
+impl GroundStation {
+    /// Connects using a `mavlink::connect` address such as `tcpout:127.0.0.1:14550`.
+    pub fn connect(address: &str) -> Result<Self> {
+        let connection = mavlink::connect(address).map_err(Error::Connect)?;
+        Ok(Self::new(connection))
+    }
+
+    pub fn new(connection: Connection) -> Self {
+        let connection = Arc::new(connection);
+        let subscribers = Arc::new(Mutex::new(Some(Vec::new())));
+        spawn_receiver(connection.clone(), Arc::downgrade(&subscribers));
+        Self {
+            connection,
+            subscribers,
+            header: MavHeader::default(),
+        }
+    }
+
+    /// Our own system id, used by vehicles to address replies.
+    pub fn system_id(&self) -> u8 {
+        self.header.system_id
+    }
+
+    pub fn component_id(&self) -> u8 {
+        self.header.component_id
+    }
+
+    /// Starts buffering every message received from now on. After the link
+    /// has closed, the subscription reports [`Error::Disconnected`] at once.
+    pub fn subscribe(&self) -> Subscription {
+        let (tx, rx) = mpsc::channel();
+        if let Some(subscribers) = self.subscribers.lock().unwrap().as_mut() {
+            subscribers.push(tx);
+        }
+        Subscription {
+            rx,
+            system_id: None,
+        }
+    }
+
+    pub fn send(&self, message: &MavMessage) -> Result<()> {
+        self.connection
+            .send(&self.header, message)
+            .map(|_| ())
+            .map_err(Error::Send)
+    }
+}
+
+fn spawn_receiver(connection: Arc<Connection>, subscribers: Weak<Subscribers>) {
+    thread::spawn(move || {
+        loop {
+            let message = match connection.recv() {
+                Ok(message) => message,
+                Err(MessageReadError::Io(e))
+                    if matches!(
+                        e.kind(),
+                        std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut
+                    ) =>
+                {
+                    if subscribers.strong_count() == 0 {
+                        return;
+                    }
+                    thread::sleep(IDLE_POLL_INTERVAL);
+                    continue;
+                }
+                Err(MessageReadError::Io(_)) => {
+                    // Dropping the senders wakes every subscriber with Disconnected.
+                    if let Some(subscribers) = subscribers.upgrade() {
+                        *subscribers.lock().unwrap() = None;
+                    }
+                    return;
+                }
+                Err(MessageReadError::Parse(_)) => continue,
+            };
+            let Some(subscribers) = subscribers.upgrade() else {
+                return;
+            };
+            if let Some(subscribers) = subscribers.lock().unwrap().as_mut() {
+                subscribers.retain(|tx| tx.send(message.clone()).is_ok());
+            }
+        }
+    });
+}
+
+/// A queue of messages received after [`GroundStation::subscribe`] was called.
+pub struct Subscription {
+    rx: Receiver<(MavHeader, MavMessage)>,
+    system_id: Option<u8>,
+}
+
+impl Subscription {
+    /// Only keep messages sent by `system_id`.
+    pub fn from_system(mut self, system_id: u8) -> Self {
+        self.system_id = Some(system_id);
+        self
+    }
+
+    /// Returns `Ok(None)` if nothing arrived within `timeout`.
+    pub fn recv_timeout(&self, timeout: Duration) -> Result<Option<(MavHeader, MavMessage)>> {
+        let deadline = Instant::now() + timeout;
+        loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            match self.rx.recv_timeout(remaining) {
+                Ok((header, _)) if self.system_id.is_some_and(|id| id != header.system_id) => {}
+                Ok(message) => return Ok(Some(message)),
+                Err(RecvTimeoutError::Timeout) => return Ok(None),
+                Err(RecvTimeoutError::Disconnected) => return Err(Error::Disconnected),
+            }
+        }
+    }
+
+    /// Waits until `matcher` picks a message, skipping everything it returns `None` for.
+    pub fn wait_for<T>(
+        &self,
+        timeout: Duration,
+        waiting_for: &'static str,
+        mut matcher: impl FnMut(&MavHeader, &MavMessage) -> Option<T>,
+    ) -> Result<T> {
+        let deadline = Instant::now() + timeout;
+        loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            let Some((header, message)) = self.recv_timeout(remaining)? else {
+                return Err(Error::Timeout(waiting_for));
+            };
+            if let Some(value) = matcher(&header, &message) {
+                return Ok(value);
+            }
+        }
+    }
+
+    /// Blocks on each message until the connection closes.
+    pub fn iter(&self) -> impl Iterator<Item = (MavHeader, MavMessage)> + '_ {
+        self.rx
+            .iter()
+            .filter(|(header, _)| self.system_id.is_none_or(|id| id == header.system_id))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use mavlink::ardupilotmega::HEARTBEAT_DATA;
+
+    use super::*;
+    use crate::mock::{self, VEHICLE};
+
+    const WAIT: Duration = Duration::from_secs(1);
+
+    fn heartbeat(custom_mode: u32) -> MavMessage {
+        MavMessage::HEARTBEAT(HEARTBEAT_DATA {
+            custom_mode,
+            ..Default::default()
+        })
+    }
+
+    fn custom_mode(subscription: &Subscription) -> Result<u32> {
+        subscription.wait_for(WAIT, "HEARTBEAT", |_, msg| match msg {
+            MavMessage::HEARTBEAT(data) => Some(data.custom_mode),
+            _ => None,
+        })
+    }
+
+    #[test]
+    fn fans_out_to_every_subscriber() {
+        let (station, peer) = mock::station(|_| Vec::new());
+        let first = station.subscribe();
+        let second = station.subscribe();
+        peer.send(heartbeat(1));
+        assert_eq!(custom_mode(&first).unwrap(), 1);
+        assert_eq!(custom_mode(&second).unwrap(), 1);
+
+        drop(first);
+        peer.send(heartbeat(2));
+        assert_eq!(custom_mode(&second).unwrap(), 2);
+    }
+
+    #[test]
+    fn only_buffers_messages_sent_after_subscribing() {
+        let (station, peer) = mock::station(|_| Vec::new());
+        let early = station.subscribe();
+        peer.send(heartbeat(1));
+        assert_eq!(custom_mode(&early).unwrap(), 1);
+        let late = station.subscribe();
+        peer.send(heartbeat(2));
+        assert_eq!(custom_mode(&late).unwrap(), 2);
+    }
+
+    #[test]
+    fn from_system_skips_other_systems() {
+        let (station, peer) = mock::station(|_| Vec::new());
+        let messages = station.subscribe().from_system(VEHICLE.system_id);
+        let other = MavHeader {
+            system_id: 2,
+            ..VEHICLE
+        };
+        peer.send_from(other, heartbeat(1));
+        peer.send(heartbeat(2));
+        assert_eq!(custom_mode(&messages).unwrap(), 2);
+    }
+
+    #[test]
+    fn wait_for_times_out_with_what_it_awaited() {
+        let (station, peer) = mock::station(|_| Vec::new());
+        let messages = station.subscribe();
+        peer.send(heartbeat(1));
+        let result = messages.wait_for(Duration::from_millis(50), "PARAM_VALUE", |_, msg| {
+            matches!(msg, MavMessage::PARAM_VALUE(_)).then_some(())
+        });
+        assert!(matches!(result, Err(Error::Timeout("PARAM_VALUE"))));
+    }
+
+    #[test]
+    fn reports_disconnected_once_the_link_fails() {
+        let (station, peer) = mock::station(|_| Vec::new());
+        let messages = station.subscribe();
+        peer.close();
+        assert!(matches!(custom_mode(&messages), Err(Error::Disconnected)));
+
+        let started = Instant::now();
+        let late = station.subscribe();
+        assert!(matches!(late.recv_timeout(WAIT), Err(Error::Disconnected)));
+        assert!(started.elapsed() < WAIT);
+        assert_eq!(late.iter().count(), 0);
+    }
+
+    #[test]
+    fn send_reaches_the_link() {
+        let (station, peer) = mock::station(|_| Vec::new());
+        station.send(&heartbeat(7)).unwrap();
+        assert_eq!(peer.sent(), [heartbeat(7)]);
+    }
+}
@@ -0,0 +1,180 @@
+use std::{
+    fmt::Write as _,
+    fs,
+    path::Path,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use mavlink::ardupilotmega::MavMessage;
+
+use crate::{
+    error::{Error, Result},
+    geo::GlobalPosition,
+    route::GPX_HEADER,
+    vehicle::Vehicle,
+};
+
+/// A position and the time it was received.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TrackPoint {
+    pub time: SystemTime,
+    pub position: GlobalPosition,
+}
+
+/// A recorded flight path, written as a GPX track.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Track {
+    pub points: Vec<TrackPoint>,
+    /// Whether recording stopped early because the link closed.
+    pub link_lost: bool,
+}
+
+impl Track {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, time: SystemTime, position: GlobalPosition) {
+        self.points.push(TrackPoint { time, position });
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        fs::write(path, self.to_gpx()).map_err(Error::Io)
+    }
+
+    /// A GPX track with one `trkpt` per position; `ele` is the altitude
+    /// above mean sea level and `time` is UTC.
+    pub fn to_gpx(&self) -> String {
+        let mut text = String::from(GPX_HEADER);
+        let _ = writeln!(text, "  <trk>\n    <name>Track</name>\n    <trkseg>");
+        for point in &self.points {
+            let _ = writeln!(
+                text,
+                "      <trkpt lat=\"{}\" lon=\"{}\"><ele>{}</ele><time>{}</time></trkpt>",
+                point.position.position.latitude,
+                point.position.position.longitude,
+                point.position.altitude,
+                utc_timestamp(point.time)
+            );
+        }
+        let _ = writeln!(text, "    </trkseg>\n  </trk>\n</gpx>");
+        text
+    }
+}
+
+impl Vehicle {
+    /// Collects the vehicle's GLOBAL_POSITION_INT messages for `duration`,
+    /// each stamped with the time it arrived. If the link closes first, the
+    /// points recorded so far are returned with [`Track::link_lost`] set.
+    pub fn record_track(&self, duration: Duration) -> Result<Track> {
+        let messages = self.subscribe();
+        let component_id = self.component_id();
+        let deadline = Instant::now() + duration;
+        let mut track = Track::new();
+        loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            let position =
+                messages.wait_for(remaining, "GLOBAL_POSITION_INT", |header, msg| match msg {
+                    MavMessage::GLOBAL_POSITION_INT(data)
+                        if header.component_id == component_id =>
+                    {
+                        Some(GlobalPosition::from(data))
+                    }
+                    _ => None,
+                });
+            match position {
+                Ok(position) => track.push(SystemTime::now(), position),
+                Err(Error::Timeout(_)) => return Ok(track),
+                Err(Error::Disconnected) => {
+                    track.link_lost = true;
+                    return Ok(track);
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+}
+
+/// `time` as ISO 8601 UTC with milliseconds, e.g. `2024-05-01T12:30:00.250Z`.
+fn utc_timestamp(time: SystemTime) -> String {
+    let since_epoch = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+    let seconds = since_epoch.as_secs();
+    let (year, month, day) = civil_from_days((seconds / 86_400) as i64);
+    let seconds_of_day = seconds % 86_400;
+    format!(
+        "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}.{:03}Z",
+        seconds_of_day / 3600,
+        seconds_of_day / 60 % 60,
+        seconds_of_day % 60,
+        since_epoch.subsec_millis()
+    )
+}
+
+/// The Gregorian date `days` after 1970-01-01, after Howard Hinnant's
+/// `civil_from_days`.
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * month_index + 2) / 5 + 1) as u32;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    } as u32;
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use mavlink::ardupilotmega::GLOBAL_POSITION_INT_DATA;
+
+    use super::*;
+    use crate::mock;
+
+    #[test]
+    fn civil_from_days_matches_known_dates() {
+        assert_eq!(civil_from_days(0), (1970, 1, 1));
+        assert_eq!(civil_from_days(-1), (1969, 12, 31));
+        assert_eq!(civil_from_days(59), (1970, 3, 1));
+        assert_eq!(civil_from_days(11_016), (2000, 2, 29));
+        assert_eq!(civil_from_days(19_844), (2024, 5, 1));
+        assert_eq!(civil_from_days(-719_468), (0, 3, 1));
+    }
+
+    #[test]
+    fn timestamps_are_utc_with_milliseconds() {
+        let time = UNIX_EPOCH + Duration::from_millis(1_714_566_600_250);
+        assert_eq!(utc_timestamp(time), "2024-05-01T12:30:00.250Z");
+        assert_eq!(utc_timestamp(UNIX_EPOCH), "1970-01-01T00:00:00.000Z");
+    }
+
+    #[test]
+    fn keeps_the_points_recorded_before_the_link_closes() {
+        let (vehicle, peer) = mock::vehicle(|_| Vec::new());
+        let sender = thread::spawn(move || {
+            // Give record_track time to subscribe.
+            thread::sleep(Duration::from_millis(100));
+            for lat in [-353612577, -353612600] {
+                peer.send(MavMessage::GLOBAL_POSITION_INT(GLOBAL_POSITION_INT_DATA {
+                    lat,
+                    lon: 1491651720,
+                    ..Default::default()
+                }));
+            }
+            peer.close();
+        });
+        let track = vehicle.record_track(Duration::from_secs(10)).unwrap();
+        sender.join().unwrap();
+        assert!(track.link_lost);
+        assert_eq!(track.points.len(), 2);
+        assert_eq!(track.points[1].position.position.latitude, -35.36126);
+    }
+}
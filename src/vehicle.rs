@@ -0,0 +1,132 @@
+use std::{
+    sync::{Arc, OnceLock},
+    time::Duration,
+};
+
+use mavlink::ardupilotmega::{HEARTBEAT_DATA, MavCmd, MavMessage, MavProtocolCapability};
+
+use crate::{
+    error::{Error, Result},
+    param_meta::ParamMetaDb,
+    station::{GroundStation, Subscription},
+};
+
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+const AUTOPILOT_VERSION_TIMEOUT: Duration = Duration::from_secs(3);
+/// Message id of AUTOPILOT_VERSION, for MAV_CMD_REQUEST_MESSAGE.
+const AUTOPILOT_VERSION_ID: f32 = 148.0;
+
+/// A remote MAVLink system reached through a [`GroundStation`].
+#[derive(Clone)]
+pub struct Vehicle {
+    station: GroundStation,
+    system_id: u8,
+    component_id: u8,
+    capabilities: Arc<OnceLock<MavProtocolCapability>>,
+    param_metadata: Option<Arc<ParamMetaDb>>,
+}
+
+impl Vehicle {
+    pub fn new(station: GroundStation, system_id: u8, component_id: u8) -> Self {
+        Self {
+            station,
+            system_id,
+            component_id,
+            capabilities: Arc::default(),
+            param_metadata: None,
+        }
+    }
+
+    /// Documentation used to describe parameters and to range-check
+    /// [`Vehicle::set_param`].
+    pub fn with_param_metadata(mut self, metadata: ParamMetaDb) -> Self {
+        self.param_metadata = Some(Arc::new(metadata));
+        self
+    }
+
+    /// Opens `address` and targets the first autopilot HEARTBEAT heard on it,
+    /// ignoring GCS, camera and gimbal heartbeats.
+    pub fn connect(address: &str) -> Result<Self> {
+        GroundStation::connect(address)?.wait_for_vehicle(CONNECT_TIMEOUT)
+    }
+
+    pub fn system_id(&self) -> u8 {
+        self.system_id
+    }
+
+    pub fn component_id(&self) -> u8 {
+        self.component_id
+    }
+
+    pub fn station(&self) -> &GroundStation {
+        &self.station
+    }
+
+    pub fn param_metadata(&self) -> Option<&ParamMetaDb> {
+        self.param_metadata.as_deref()
+    }
+
+    /// Subscribes to messages sent by this vehicle only.
+    pub fn subscribe(&self) -> Subscription {
+        self.station.subscribe().from_system(self.system_id)
+    }
+
+    pub fn send(&self, message: &MavMessage) -> Result<()> {
+        self.station.send(message)
+    }
+
+    /// Waits for the next HEARTBEAT from the targeted component.
+    pub fn wait_for_heartbeat(&self, timeout: Duration) -> Result<HEARTBEAT_DATA> {
+        let component_id = self.component_id;
+        self.subscribe()
+            .wait_for(timeout, "HEARTBEAT", |header, msg| match msg {
+                MavMessage::HEARTBEAT(data) if header.component_id == component_id => {
+                    Some(data.clone())
+                }
+                _ => None,
+            })
+    }
+
+    /// The MAVLink features the autopilot reports in AUTOPILOT_VERSION,
+    /// requested once and then cached. Autopilots that never answer are
+    /// treated as reporting none.
+    pub fn capabilities(&self) -> Result<MavProtocolCapability> {
+        if let Some(capabilities) = self.capabilities.get() {
+            return Ok(*capabilities);
+        }
+        let capabilities = match self.request_autopilot_version() {
+            Ok(capabilities) => capabilities,
+            Err(Error::Timeout(_) | Error::CommandFailed { .. }) => MavProtocolCapability::empty(),
+            Err(e) => return Err(e),
+        };
+        Ok(*self.capabilities.get_or_init(|| capabilities))
+    }
+
+    fn request_autopilot_version(&self) -> Result<MavProtocolCapability> {
+        let messages = self.subscribe();
+        let requested = self.send_command(
+            MavCmd::MAV_CMD_REQUEST_MESSAGE,
+            [AUTOPILOT_VERSION_ID, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0],
+        );
+        if let Err(Error::CommandFailed { .. }) = requested {
+            // Autopilots older than MAV_CMD_REQUEST_MESSAGE.
+            self.send_command(
+                MavCmd::MAV_CMD_REQUEST_AUTOPILOT_CAPABILITIES,
+                [1.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0],
+            )?;
+        } else {
+            requested?;
+        }
+        let component_id = self.component_id;
+        messages.wait_for(
+            AUTOPILOT_VERSION_TIMEOUT,
+            "AUTOPILOT_VERSION",
+            |header, msg| match msg {
+                MavMessage::AUTOPILOT_VERSION(data) if header.component_id == component_id => {
+                    Some(data.capabilities)
+                }
+                _ => None,
+            },
+        )
+    }
+}